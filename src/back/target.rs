@@ -1 +1,75 @@
+use crate::front::nodes::r#type::{PrimitiveType, Type};
 
+/// The architectures Petal can generate code for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    X86_64,
+    AArch64,
+    /// Cortex-M0+ (ARMv6-M), which has no hardware divide instruction.
+    Rp2040,
+}
+
+impl Target {
+    /// Parses a target name as given to `--target`.
+    pub fn new(name: &str) -> Result<Self, String> {
+        match name {
+            "x86_64" => Ok(Target::X86_64),
+            "aarch64" => Ok(Target::AArch64),
+            "rp2040" => Ok(Target::Rp2040),
+            _ => Err(format!(
+                "unknown target '{}'; expected one of x86_64, aarch64, rp2040",
+                name
+            )),
+        }
+    }
+
+    /// The width of an address in bytes.
+    pub fn pointer_size(&self) -> usize {
+        match self {
+            Target::X86_64 | Target::AArch64 => 8,
+            Target::Rp2040 => 4,
+        }
+    }
+
+    /// The size of a value of type `t` in bytes, or `None` if it has no layout yet.
+    ///
+    /// `usize` and `str` (a pointer to the string data) are pointer-sized.
+    pub fn size_of(&self, t: &Type) -> Option<usize> {
+        let Type::Primitive(primitive) = t else {
+            return None;
+        };
+        let size = match primitive {
+            PrimitiveType::Void => 0,
+            PrimitiveType::Bool => 1,
+            PrimitiveType::I32 | PrimitiveType::U32 | PrimitiveType::F32 | PrimitiveType::Char => 4,
+            PrimitiveType::I64 | PrimitiveType::U64 | PrimitiveType::F64 => 8,
+            PrimitiveType::Usize | PrimitiveType::Str => self.pointer_size(),
+        };
+        Some(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Target;
+    use crate::front::nodes::r#type::Type;
+
+    #[test]
+    fn target_names_parse() {
+        assert_eq!(Target::new("x86_64"), Ok(Target::X86_64));
+        assert_eq!(Target::new("aarch64"), Ok(Target::AArch64));
+        assert_eq!(Target::new("rp2040"), Ok(Target::Rp2040));
+        assert_eq!(
+            Target::new("riscv64"),
+            Err("unknown target 'riscv64'; expected one of x86_64, aarch64, rp2040".to_string())
+        );
+    }
+
+    #[test]
+    fn pointer_sized_types_follow_the_target() {
+        assert_eq!(Target::X86_64.size_of(&Type::basic("usize")), Some(8));
+        assert_eq!(Target::Rp2040.size_of(&Type::basic("str")), Some(4));
+        assert_eq!(Target::Rp2040.size_of(&Type::basic("i64")), Some(8));
+        assert_eq!(Target::AArch64.size_of(&Type::Custom("Point".to_string())), None);
+    }
+}
@@ -1,11 +1,52 @@
 use clap::{Arg, Command};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::back::target::Target;
+use crate::front::nodes::r#type::Type;
+
+/// The artifact requested with `--emit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// The token stream; compilation stops after lexing.
+    Tokens,
+    /// The abstract syntax tree.
+    Ast,
+    /// The IR after optimization.
+    Ir,
+    /// Assembly written to the output file, the default. There is no code
+    /// generator yet, so compilation stops after the IR and nothing is written.
+    Asm,
+    /// The caller → callees listing.
+    CallGraph,
+    /// The call graph in Graphviz DOT format.
+    CallGraphDot,
+}
+
+/// How diagnostics are printed, chosen with `--error-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// One readable line per diagnostic.
+    Human,
+    /// One JSON object per line, for editors and other tools.
+    Json,
+}
+
 #[derive(Debug)]
 pub struct PetalConfig {
     pub src: PathBuf,
     pub output_file_name: String,
     pub debug_mode: bool,
+    pub time_passes: bool,
+    pub lint: bool,
+    pub keep_temps: bool,
+    pub emit: Emit,
+    pub target: Target,
+    pub error_format: ErrorFormat,
+    /// The type given to integer literals that have no other type to take on.
+    pub default_integer: Type,
+    /// Flags from `-D name[=value]`, tested by `#[cfg(...)]`.
+    pub defines: HashMap<String, Option<String>>,
 }
 
 impl PetalConfig {
@@ -34,6 +75,69 @@ impl PetalConfig {
                     .help("Enables debug mode")
                     .action(clap::ArgAction::SetTrue),
             )
+            .arg(
+                Arg::new("time-passes")
+                    .long("time-passes")
+                    .help("Reports the time spent in each compiler pass")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("lint")
+                    .long("lint")
+                    .help("Enables opt-in lint warnings")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("keep-temps")
+                    .long("keep-temps")
+                    .help("Writes intermediate artifacts (tokens, IR) next to the output file")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("emit")
+                    .long("emit")
+                    .value_name("KIND")
+                    .help("Selects the artifact to print or write; --debug prints every stage (asm writes nothing until code generation exists)")
+                    .value_parser(["tokens", "ast", "ir", "asm", "callgraph", "callgraph-dot"])
+                    .default_value("asm")
+                    .num_args(1),
+            )
+            .arg(
+                Arg::new("target")
+                    .short('t')
+                    .long("target")
+                    .value_name("TARGET")
+                    .help("Sets the architecture to generate code for (x86_64, aarch64, rp2040)")
+                    .value_parser(Target::new)
+                    .default_value("x86_64")
+                    .num_args(1),
+            )
+            .arg(
+                Arg::new("error-format")
+                    .long("error-format")
+                    .value_name("FORMAT")
+                    .help("Sets how diagnostics are printed")
+                    .value_parser(["human", "json"])
+                    .default_value("human")
+                    .num_args(1),
+            )
+            .arg(
+                Arg::new("default-int")
+                    .long("default-int")
+                    .value_name("TYPE")
+                    .help("Sets the type of unannotated integer literals")
+                    .value_parser(["i32", "i64", "u32", "u64", "usize"])
+                    .default_value("i32")
+                    .num_args(1),
+            )
+            .arg(
+                Arg::new("define")
+                    .short('D')
+                    .long("define")
+                    .value_name("NAME[=VALUE]")
+                    .help("Defines a flag for `#[cfg(...)]` conditional compilation")
+                    .action(clap::ArgAction::Append),
+            )
             .get_matches();
 
         let src = matches
@@ -45,11 +149,66 @@ impl PetalConfig {
             .unwrap_or(&"a.out".to_string())
             .clone();
         let debug_mode = matches.get_flag("debug");
+        let time_passes = matches.get_flag("time-passes");
+        let lint = matches.get_flag("lint");
+        let keep_temps = matches.get_flag("keep-temps");
+        let emit = match matches
+            .get_one::<String>("emit")
+            .expect("--emit has a default value")
+            .as_str()
+        {
+            "tokens" => Emit::Tokens,
+            "ast" => Emit::Ast,
+            "ir" => Emit::Ir,
+            "asm" => Emit::Asm,
+            "callgraph" => Emit::CallGraph,
+            "callgraph-dot" => Emit::CallGraphDot,
+            _ => unreachable!("clap only accepts the listed --emit values"),
+        };
+        let target = *matches
+            .get_one::<Target>("target")
+            .expect("--target has a default value");
+
+        let error_format = match matches
+            .get_one::<String>("error-format")
+            .map(String::as_str)
+        {
+            Some("json") => ErrorFormat::Json,
+            _ => ErrorFormat::Human,
+        };
+        let default_integer = Type::basic(
+            matches
+                .get_one::<String>("default-int")
+                .expect("--default-int has a default value"),
+        );
+
+        let defines = matches
+            .get_many::<String>("define")
+            .unwrap_or_default()
+            .map(|define| match define.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (define.clone(), None),
+            })
+            .collect();
 
         PetalConfig {
             src,
             output_file_name,
             debug_mode,
+            time_passes,
+            lint,
+            keep_temps,
+            emit,
+            target,
+            error_format,
+            default_integer,
+            defines,
         }
     }
+
+    /// Whether the intermediate `stage` is printed, either because `--emit`
+    /// asked for it or because `--debug` prints every stage.
+    pub fn prints(&self, stage: Emit) -> bool {
+        self.debug_mode || self.emit == stage
+    }
 }
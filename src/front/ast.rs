@@ -34,6 +34,10 @@ impl Node for Ast {
 
         instructions
     }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        self.children.iter().map(|child| child.as_ref()).collect()
+    }
 }
 
 impl Node for Box<Ast> {
@@ -62,6 +66,10 @@ impl Node for Box<Ast> {
 
         instructions
     }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        self.children.iter().map(|child| child.as_ref()).collect()
+    }
 }
 
 impl Ast {
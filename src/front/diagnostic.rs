@@ -0,0 +1,165 @@
+use std::fmt;
+
+use super::lexer::LexError;
+use super::parser::ParserError;
+use super::token::Position;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// The compiler phase that produced a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Lex,
+    Parse,
+    Semantic,
+}
+
+/// An error or warning reported by any front-end phase.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub position: Option<Position>,
+    pub phase: Phase,
+}
+
+impl Diagnostic {
+    pub fn error(phase: Phase, message: String, position: Option<Position>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            position,
+            phase,
+        }
+    }
+
+    pub fn warning(phase: Phase, message: String, position: Option<Position>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message,
+            position,
+            phase,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Sorts diagnostics by source position; those without one go last.
+    pub fn sort(diagnostics: &mut [Diagnostic]) {
+        diagnostics.sort_by_key(|d| match &d.position {
+            Some(pos) => (0, pos.line, pos.index),
+            None => (1, 0, 0),
+        });
+    }
+
+    /// Serializes the diagnostic as a single-line JSON object for `--error-format=json`.
+    pub fn to_json(&self, file: &str) -> String {
+        let (line, index) = match &self.position {
+            Some(pos) => (pos.line.to_string(), pos.index.to_string()),
+            None => ("null".to_string(), "null".to_string()),
+        };
+        format!(
+            "{{\"severity\":\"{}\",\"phase\":\"{}\",\"message\":{},\"file\":{},\"line\":{},\"index\":{}}}",
+            self.severity.to_string().to_lowercase(),
+            self.phase,
+            json_string(&self.message),
+            json_string(file),
+            line,
+            index
+        )
+    }
+}
+
+/// Quotes `s` as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(error: LexError) -> Self {
+        Diagnostic::error(Phase::Lex, error.message, Some(error.position))
+    }
+}
+
+impl From<ParserError> for Diagnostic {
+    fn from(error: ParserError) -> Self {
+        Diagnostic::error(Phase::Parse, error.message(), error.position().cloned())
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Lex => write!(f, "lex"),
+            Phase::Parse => write!(f, "parse"),
+            Phase::Semantic => write!(f, "semantic"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.severity, self.phase, self.message)?;
+        if let Some(pos) = &self.position {
+            write!(f, " on line {} at position {}", pos.line, pos.index)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Phase};
+    use crate::front::token::Position;
+
+    #[test]
+    fn json_diagnostics_carry_every_field() {
+        let diagnostic = Diagnostic::error(
+            Phase::Parse,
+            "Missing token ';'".to_string(),
+            Some(Position { line: 3, index: 14 }),
+        );
+        assert_eq!(
+            diagnostic.to_json("src/main.lts"),
+            r#"{"severity":"error","phase":"parse","message":"Missing token ';'","file":"src/main.lts","line":3,"index":14}"#
+        );
+    }
+
+    #[test]
+    fn json_strings_are_escaped_and_missing_positions_are_null() {
+        let diagnostic = Diagnostic::warning(Phase::Semantic, "a \"quoted\"\tname\\\n\u{1}".to_string(), None);
+        assert_eq!(
+            diagnostic.to_json("C:\\main.lts"),
+            r#"{"severity":"warning","phase":"semantic","message":"a \"quoted\"\tname\\\n\u0001","file":"C:\\main.lts","line":null,"index":null}"#
+        );
+    }
+}
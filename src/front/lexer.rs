@@ -14,9 +14,16 @@ macro_rules! here {
     };
 }
 
+#[derive(Debug)]
+pub struct LexError {
+    pub message: String,
+    pub position: Position,
+}
+
 pub struct Lexer<'a> {
     position: Position,
     input: Peekable<Chars<'a>>,
+    errors: Vec<LexError>,
 }
 
 impl<'a> Iterator for Lexer<'a> {
@@ -32,6 +39,7 @@ impl<'a> Lexer<'a> {
         Lexer {
             position: Position { line: 1, index: 1 },
             input: input.chars().peekable(),
+            errors: Vec::new(),
         }
     }
 
@@ -47,6 +55,17 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Peeks one character past `peek()` without consuming anything.
+    ///
+    /// Cloning a `Peekable<Chars>` only copies the slice iterator and the peeked
+    /// slot, so this is constant time regardless of how much input remains, and
+    /// lexing a number stays linear in its length however many dots follow it.
+    fn peek_second(&self) -> Option<char> {
+        let mut lookahead = self.input.clone();
+        lookahead.next();
+        lookahead.next()
+    }
+
     /// Skip a nested multiline comment.
     /// Returns true if the comment was terminated correctly, false otherwise.
     fn skip_multiline_comment(&mut self) -> bool {
@@ -152,9 +171,12 @@ impl<'a> Lexer<'a> {
                             self.input.next(); // Consume '*' signaling multiline comment.
                             self.update_position(next_ch);
                             // Skip the entire multiline comment.
+                            let start = self.position.clone();
                             if !self.skip_multiline_comment() {
-                                println!("Error: Unterminated multiline comment.");
-                                // In a real compiler, you might return an Error token or panic.
+                                self.errors.push(LexError {
+                                    message: "Unterminated multiline comment".to_string(),
+                                    position: start,
+                                });
                             }
                             continue; // Restart scanning tokens after the comment.
                         }
@@ -169,6 +191,18 @@ impl<'a> Lexer<'a> {
                 '=' => {
                     self.input.next();
                     self.update_position(ch);
+                    if let Some(&next_ch) = self.input.peek() {
+                        let token = match next_ch {
+                            '=' => Some(Token::EqEq),
+                            '>' => Some(Token::FatArrow),
+                            _ => None,
+                        };
+                        if let Some(token) = token {
+                            self.input.next();
+                            self.update_position(next_ch);
+                            return Some((token, self.position.clone()));
+                        }
+                    }
                     return Some((Token::Equal, self.position.clone()));
                 }
                 '(' => {
@@ -201,6 +235,109 @@ impl<'a> Lexer<'a> {
                     self.update_position(ch);
                     return Some((Token::Semicolon, self.position.clone()));
                 }
+                '?' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    return Some((Token::Question, self.position.clone()));
+                }
+                '!' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    if let Some(&next_ch) = self.input.peek() {
+                        if next_ch == '=' {
+                            self.input.next();
+                            self.update_position(next_ch);
+                            return Some((Token::NotEq, self.position.clone()));
+                        }
+                    }
+                    return Some((Token::Not, self.position.clone()));
+                }
+                '<' if self.input.clone().skip(1).take(2).eq("=>".chars()) => {
+                    for c in "<=>".chars() {
+                        self.input.next();
+                        self.update_position(c);
+                    }
+                    return Some((Token::Compare, self.position.clone()));
+                }
+                '<' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    if let Some(&next_ch) = self.input.peek() {
+                        let token = match next_ch {
+                            '=' => Some(Token::Le),
+                            '<' => Some(Token::ShiftLeft),
+                            _ => None,
+                        };
+                        if let Some(token) = token {
+                            self.input.next();
+                            self.update_position(next_ch);
+                            return Some((token, self.position.clone()));
+                        }
+                    }
+                    return Some((Token::Lt, self.position.clone()));
+                }
+                '>' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    if let Some(&next_ch) = self.input.peek() {
+                        let token = match next_ch {
+                            '=' => Some(Token::Ge),
+                            '>' => Some(Token::ShiftRight),
+                            _ => None,
+                        };
+                        if let Some(token) = token {
+                            self.input.next();
+                            self.update_position(next_ch);
+                            return Some((token, self.position.clone()));
+                        }
+                    }
+                    return Some((Token::Gt, self.position.clone()));
+                }
+                '^' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    return Some((Token::Caret, self.position.clone()));
+                }
+                '&' | '|' if self.input.clone().nth(1) == Some(ch) => {
+                    for _ in 0..2 {
+                        self.input.next();
+                        self.update_position(ch);
+                    }
+                    let token = if ch == '&' { Token::And } else { Token::Or };
+                    return Some((token, self.position.clone()));
+                }
+                '.' if self.peek_second() == Some('.') => {
+                    let (token, text) = if self.input.clone().nth(2) == Some('=') {
+                        (Token::DotDotEqual, "..=")
+                    } else {
+                        (Token::DotDot, "..")
+                    };
+                    for c in text.chars() {
+                        self.input.next();
+                        self.update_position(c);
+                    }
+                    return Some((token, self.position.clone()));
+                }
+                '[' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    return Some((Token::LBracket, self.position.clone()));
+                }
+                ']' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    return Some((Token::RBracket, self.position.clone()));
+                }
+                '#' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    return Some((Token::Hash, self.position.clone()));
+                }
+                '@' => {
+                    self.input.next();
+                    self.update_position(ch);
+                    return Some((Token::At, self.position.clone()));
+                }
                 ':' => {
                     self.input.next();
                     self.update_position(ch);
@@ -214,10 +351,14 @@ impl<'a> Lexer<'a> {
                     return Some((Token::Colon, self.position.clone()));
                 }
                 _ => {
+                    // Record the bad character and keep lexing after it.
+                    self.errors.push(LexError {
+                        message: format!("Unknown character '{}'", ch),
+                        position: self.position.clone(),
+                    });
                     self.input.next();
                     self.update_position(ch);
-                    println!("Warning: Unknown token '{}'", ch);
-                    return Some((Token::Unknown(ch), self.position.clone()));
+                    continue;
                 }
             }
         }
@@ -228,13 +369,27 @@ impl<'a> Lexer<'a> {
         let mut num_str = String::new();
         let mut has_decimal = false;
 
+        // Prefixed integers (`0x`, `0b`, `0o`) keep their raw spelling; the parser checks the digits.
+        if self.input.peek() == Some(&'0') && matches!(self.peek_second(), Some('x' | 'b' | 'o')) {
+            while let Some(&ch) = self.input.peek() {
+                if !ch.is_ascii_alphanumeric() {
+                    break;
+                }
+                num_str.push(ch);
+                self.input.next();
+                self.update_position(ch);
+            }
+            return Token::NumberLiteral(num_str);
+        }
+
         while let Some(&ch) = self.input.peek() {
             if ch.is_ascii_digit() {
                 num_str.push(ch);
                 self.input.next(); // Consume digit
                 self.update_position(ch);
             } else if ch == '.' {
-                if has_decimal || self.input.clone().nth(1) == Some('.') {
+                // A second '.' means a range such as `1..5`, not a float.
+                if has_decimal || self.peek_second() == Some('.') {
                     break;
                 }
                 has_decimal = true;
@@ -249,12 +404,12 @@ impl<'a> Lexer<'a> {
         Token::NumberLiteral(num_str)
     }
 
-    fn identifier_or_keyword(&mut self) -> Token {
-        let mut ident = String::new();
+    fn read_word(&mut self) -> String {
+        let mut word = String::new();
 
         while let Some(&ch) = self.input.peek() {
             if ch.is_ascii_alphanumeric() || ch == '_' {
-                ident.push(ch);
+                word.push(ch);
                 self.input.next(); // Consume character
                 self.update_position(ch);
             } else {
@@ -262,17 +417,42 @@ impl<'a> Lexer<'a> {
             }
         }
 
+        word
+    }
+
+    fn identifier_or_keyword(&mut self) -> Token {
+        let ident = self.read_word();
+
+        // `r#name` is a raw identifier: always an identifier, even when `name` is a keyword.
+        // Only `#` followed by a name counts, so `r"..."` is left alone.
+        if ident == "r" {
+            let mut lookahead = self.input.clone();
+            if lookahead.next() == Some('#')
+                && matches!(lookahead.peek(), Some(ch) if ch.is_ascii_alphabetic() || *ch == '_')
+            {
+                self.input.next();
+                self.update_position('#');
+                return Token::Identifier(self.read_word());
+            }
+        }
+
         match ident.as_str() {
             "fn" => Token::Fn,
             "ret" => Token::Ret,
+            "const" => Token::Const,
+            "static" => Token::Static,
             "struct" => Token::Struct,
             "pub" => Token::Pub,
             "enum" => Token::Enum,
             "impl" => Token::Impl,
+            "where" => Token::Where,
             "if" => Token::If,
             "else" => Token::Else,
             "for" => Token::For,
             "while" => Token::While,
+            "match" => Token::Match,
+            "break" => Token::Break,
+            "continue" => Token::Continue,
             "i32" => Token::I32,
             "i64" => Token::I64,
             "u32" => Token::U32,
@@ -281,6 +461,11 @@ impl<'a> Lexer<'a> {
             "f32" => Token::F32,
             "f64" => Token::F64,
             "char" => Token::Char,
+            "str" => Token::Str,
+            "bool" => Token::Bool,
+            "as" => Token::As,
+            "true" => Token::BooleanLiteral(true),
+            "false" => Token::BooleanLiteral(false),
             _ => Token::Identifier(ident),
         }
     }
@@ -290,28 +475,41 @@ impl<'a> Lexer<'a> {
         // Consume the opening double-quote.
         self.input.next();
         self.update_position('\"');
-        
+        let start = self.position.clone();
+
+        let mut terminated = false;
         while let Some(&ch) = self.input.peek() {
             if ch == '"' {
                 self.input.next(); // Consume the closing quote.
                 self.update_position(ch);
+                terminated = true;
                 break;
             } else if ch == '\\' {
                 self.input.next();
                 self.update_position('\\');
                 if let Some(&esc_ch) = self.input.peek() {
-                    let escaped_char = match esc_ch {
-                        'n' => '\n',
-                        't' => '\t',
-                        'r' => '\r',
-                        '"' => '"',
-                        '\\' => '\\',
-                        '\'' => '\'',
-                        other => other,
-                    };
-                    literal.push(escaped_char);
-                    self.input.next();
-                    self.update_position(esc_ch);
+                    // Line continuation: drop the newline and the next line's indentation.
+                    if esc_ch == '\n' || (esc_ch == '\r' && self.peek_second() == Some('\n')) {
+                        if esc_ch == '\r' {
+                            self.input.next();
+                            self.update_position('\r');
+                        }
+                        self.input.next(); // Consume '\n'
+                        self.update_position('\n');
+                        while let Some(&ws) = self.input.peek() {
+                            if ws != ' ' && ws != '\t' {
+                                break;
+                            }
+                            self.input.next();
+                            self.update_position(ws);
+                        }
+                        continue;
+                    }
+                }
+
+                match self.read_escape() {
+                    Ok(escaped_char) => literal.push(escaped_char),
+                    Err(error) => self.errors.push(error),
                 }
             } else {
                 literal.push(ch);
@@ -319,31 +517,94 @@ impl<'a> Lexer<'a> {
                 self.update_position(ch);
             }
         }
+        if !terminated {
+            self.errors.push(LexError {
+                message: "Unterminated string literal".to_string(),
+                position: start,
+            });
+        }
         Token::StringLiteral(literal)
     }
 
+    /// Reads the escape sequence following a consumed `\`, shared by string and
+    /// character literals: `\n \t \r \\ \' \" \0`, `\xHH` (ASCII) and `\u{...}`.
+    fn read_escape(&mut self) -> Result<char, LexError> {
+        let start = self.position.clone();
+        let error = |message: String| LexError {
+            message,
+            position: start.clone(),
+        };
+
+        let Some(esc_ch) = self.input.next() else {
+            return Err(error("Unterminated escape sequence".to_string()));
+        };
+        self.update_position(esc_ch);
+
+        match esc_ch {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' | '\'' | '"' => Ok(esc_ch),
+            'x' => {
+                let digits = self.read_hex_digits(2);
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(value) if digits.len() == 2 && value <= 0x7F => Ok(value as char),
+                    _ => Err(error(format!("Invalid escape '\\x{}', expected two hex digits up to 7F", digits))),
+                }
+            }
+            'u' => {
+                if self.input.peek() != Some(&'{') {
+                    return Err(error("Expected '{' after '\\u'".to_string()));
+                }
+                self.input.next();
+                self.update_position('{');
+
+                let digits = self.read_hex_digits(6);
+                if self.input.peek() != Some(&'}') {
+                    return Err(error(format!("Unterminated escape '\\u{{{}'", digits)));
+                }
+                self.input.next();
+                self.update_position('}');
+
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| error(format!("Invalid unicode escape '\\u{{{}}}'", digits)))
+            }
+            other => Err(error(format!("Unknown escape sequence '\\{}'", other))),
+        }
+    }
+
+    /// Consumes up to `max` hexadecimal digits.
+    fn read_hex_digits(&mut self, max: usize) -> String {
+        let mut digits = String::new();
+        while digits.len() < max {
+            match self.input.peek() {
+                Some(&ch) if ch.is_ascii_hexdigit() => {
+                    digits.push(ch);
+                    self.input.next();
+                    self.update_position(ch);
+                }
+                _ => break,
+            }
+        }
+        digits
+    }
+
     fn character_literal(&mut self) -> Token {
         // Consume the opening single-quote.
         self.input.next();
         self.update_position('\'');
+        let start = self.position.clone();
         let mut char_val = None;
         if let Some(&ch) = self.input.peek() {
             if ch == '\\' {
                 self.input.next();
                 self.update_position('\\');
-                if let Some(&esc_ch) = self.input.peek() {
-                    let c = match esc_ch {
-                        'n' => '\n',
-                        't' => '\t',
-                        'r' => '\r',
-                        '\\' => '\\',
-                        '\'' => '\'',
-                        '"' => '"',
-                        other => other,
-                    };
-                    char_val = Some(c);
-                    self.input.next();
-                    self.update_position(esc_ch);
+                match self.read_escape() {
+                    Ok(c) => char_val = Some(c),
+                    Err(error) => self.errors.push(error),
                 }
             } else {
                 char_val = Some(ch);
@@ -352,18 +613,258 @@ impl<'a> Lexer<'a> {
             }
         }
         // Expect the closing single-quote.
-        if let Some(&ch) = self.input.peek() {
-            if ch == '\'' {
-                self.input.next();
-                self.update_position('\'');
-            }
+        if self.input.peek() == Some(&'\'') {
+            self.input.next();
+            self.update_position('\'');
+        } else {
+            self.errors.push(LexError {
+                message: "Unterminated character literal".to_string(),
+                position: start,
+            });
         }
         Token::CharacterLiteral(char_val.unwrap_or('\0'))
     }
 
-    pub fn lex(self) -> Vec<(Token, Position)> {
-        let mut vec: Vec<(Token, Position)> = self.collect();
-        vec.push((Token::Eof, Position { line: vec.last().unwrap().1.line + 1, index: 1 }));
-        vec
+    /// Returns the errors found so far, for use after streaming tokens out of the lexer.
+    pub fn into_errors(self) -> Vec<LexError> {
+        self.errors
+    }
+
+    /// Lexes the whole input, returning the tokens and any errors found along the way.
+    pub fn lex(mut self) -> (Vec<(Token, Position)>, Vec<LexError>) {
+        let mut vec: Vec<(Token, Position)> = self.by_ref().collect();
+        // An empty (or comment-only) file still gets an end-of-file token.
+        let line = vec.last().map_or(self.position.line, |(_, pos)| pos.line + 1);
+        vec.push((Token::Eof, Position { line, index: 1 }));
+        (vec, self.errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::Lexer;
+    use crate::front::token::Token;
+    use crate::testing::{parse_with_errors, run};
+
+    /// The tokens of `src` without positions or the final `Eof`.
+    fn tokens(src: &str) -> Vec<Token> {
+        let (tokens, errors) = Lexer::new(src).lex();
+        assert!(errors.is_empty(), "lexing {:?} failed: {:?}", src, errors);
+        tokens.into_iter().map(|(token, _)| token).filter(|token| *token != Token::Eof).collect()
+    }
+
+    fn number(literal: &str) -> Token {
+        Token::NumberLiteral(literal.to_string())
+    }
+
+    #[test]
+    fn decimal_point_and_range_dots_are_told_apart() {
+        assert_eq!(tokens("1.5"), vec![number("1.5")]);
+        assert_eq!(tokens("1..=5"), vec![number("1"), Token::DotDotEqual, number("5")]);
+        assert_eq!(tokens("1..5"), vec![number("1"), Token::DotDot, number("5")]);
+        let id = |name: &str| Token::Identifier(name.to_string());
+        assert_eq!(
+            tokens("for i in 1..5"),
+            vec![Token::For, id("i"), id("in"), number("1"), Token::DotDot, number("5")]
+        );
+    }
+
+    #[test]
+    fn relational_operators_are_lexed_with_their_end_positions() {
+        let (lexed, errors) = Lexer::new("a==b!=c<d>e<=f>=g").lex();
+        assert!(errors.is_empty());
+        let operators: Vec<(Token, usize)> = lexed
+            .into_iter()
+            .filter(|(token, _)| !matches!(token, Token::Identifier(_) | Token::Eof))
+            .map(|(token, pos)| (token, pos.index))
+            .collect();
+        assert_eq!(
+            operators,
+            vec![
+                (Token::EqEq, 4),
+                (Token::NotEq, 7),
+                (Token::Lt, 9),
+                (Token::Gt, 11),
+                (Token::Le, 14),
+                (Token::Ge, 17),
+            ]
+        );
+    }
+
+    #[test]
+    fn bitwise_operators_are_lexed() {
+        let id = |name: &str| Token::Identifier(name.to_string());
+        assert_eq!(tokens("a << 2"), vec![id("a"), Token::ShiftLeft, number("2")]);
+        assert_eq!(tokens("a >> b"), vec![id("a"), Token::ShiftRight, id("b")]);
+        assert_eq!(tokens("a ^ b"), vec![id("a"), Token::Caret, id("b")]);
+        assert_eq!(tokens("!flag"), vec![Token::Not, id("flag")]);
+        // Two-character operators advance the position past both characters.
+        assert_eq!(Lexer::new("a<<b").nth(1).unwrap().1.index, 4);
+    }
+
+    #[test]
+    fn negation_and_comparison_operators_are_lexed() {
+        let id = |name: &str| Token::Identifier(name.to_string());
+        assert_eq!(tokens("!a"), vec![Token::Not, id("a")]);
+        assert_eq!(tokens("a <=> b"), vec![id("a"), Token::Compare, id("b")]);
+        assert_eq!(
+            tokens("a != b <= c < d"),
+            vec![id("a"), Token::NotEq, id("b"), Token::Le, id("c"), Token::Lt, id("d")]
+        );
+    }
+
+    #[test]
+    fn escapes_mean_the_same_in_strings_and_chars() {
+        let escapes = [
+            ("\\n", '\n'),
+            ("\\t", '\t'),
+            ("\\r", '\r'),
+            ("\\0", '\0'),
+            ("\\\\", '\\'),
+            ("\\'", '\''),
+            ("\\\"", '"'),
+            ("\\x41", 'A'),
+            ("\\u{E9}", 'é'),
+            ("\\u{1F600}", '😀'),
+        ];
+        for (escape, ch) in escapes {
+            assert_eq!(tokens(&format!("'{}'", escape)), vec![Token::CharacterLiteral(ch)]);
+            assert_eq!(tokens(&format!("\"{}\"", escape)), vec![Token::StringLiteral(ch.to_string())]);
+        }
+    }
+
+    #[test]
+    fn unterminated_literals_are_reported_at_their_opening_quote() {
+        let errors = |src: &str| {
+            let (_, errors) = Lexer::new(src).lex();
+            errors.into_iter().map(|e| (e.message, e.position.line, e.position.index)).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            errors("fn main() {\n    s := \"abc;\n}\n"),
+            vec![("Unterminated string literal".to_string(), 2, 11)]
+        );
+        assert_eq!(errors("c := 'a"), vec![("Unterminated character literal".to_string(), 1, 7)]);
+        assert!(errors("s := \"abc\"; c := 'a';").is_empty());
+    }
+
+    #[test]
+    fn invalid_escapes_are_reported_in_both_literals() {
+        let message = |src: &str| {
+            let (_, errors) = Lexer::new(src).lex();
+            errors.into_iter().map(|e| e.message).next()
+        };
+        for quote in ["'", "\""] {
+            let literal = |escape: &str| format!("{}{}{}", quote, escape, quote);
+            assert_eq!(
+                message(&literal("\\u{D800}")).as_deref(),
+                Some("Invalid unicode escape '\\u{D800}'")
+            );
+            assert_eq!(
+                message(&literal("\\x80")).as_deref(),
+                Some("Invalid escape '\\x80', expected two hex digits up to 7F")
+            );
+            assert_eq!(message(&literal("\\q")).as_deref(), Some("Unknown escape sequence '\\q'"));
+        }
+    }
+
+    #[test]
+    fn raw_identifiers_escape_keywords() {
+        let id = |name: &str| Token::Identifier(name.to_string());
+        assert_eq!(tokens("r#if"), vec![id("if")]);
+        assert_eq!(tokens("if"), vec![Token::If]);
+        assert_eq!(tokens("r#for := r#x"), vec![id("for"), Token::Walrus, id("x")]);
+        // Without a name after `#`, `r` is an ordinary identifier.
+        assert_eq!(tokens("r #"), vec![id("r"), Token::Hash]);
+    }
+
+    #[test]
+    fn raw_identifiers_name_variables() {
+        assert_eq!(run("fn main() -> i32 { r#if := 4; ret r#if + 1; }"), 5);
+    }
+
+    #[test]
+    fn unknown_character_is_reported_and_skipped() {
+        let (tokens, errors) = Lexer::new("a := 1 $ 2;").lex();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unknown character '$'");
+        assert_eq!((errors[0].position.line, errors[0].position.index), (1, 8));
+
+        let tokens: Vec<Token> = tokens.into_iter().map(|(token, _)| token).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::Walrus,
+                number("1"),
+                number("2"),
+                Token::Semicolon,
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn backslash_newline_continues_a_string() {
+        assert_eq!(
+            tokens("\"hello \\\n      world\""),
+            vec![Token::StringLiteral("hello world".to_string())]
+        );
+        assert_eq!(
+            tokens("\"hello\nworld\""),
+            vec![Token::StringLiteral("hello\nworld".to_string())]
+        );
+    }
+
+    /// Source fragments the random inputs are built from, biased towards the
+    /// prefixes where the lexer looks ahead or switches mode.
+    const FRAGMENTS: &[&str] = &[
+        "fn", "main", "(", ")", "{", "}", "[", "]", ";", ":", ":=", "=", "->", "=>", ",", ".", "..",
+        "..=", "+", "-", "*", "/", "%", "<", "<=", "<=>", ">", "!", "!=", "==", "&&", "||", "?", "@",
+        "#", "#[cfg(", "r#", "ret", "if", "else", "for", "while", "match", "break", "continue", "static",
+        "pub", "as", "0", "42", "0x", "0xFF", "0b", "0o7", "1.5", "5000000000", "\"", "\\", "\\u{",
+        "'", "'a'", "\\n", "//", "/*", "*/", " ", "\n", "\t", "x", "_y", "é", "$",
+    ];
+
+    /// Lexes and parses `src`, failing with the input if either phase panics.
+    fn assert_survives(src: &str) {
+        let result = panic::catch_unwind(|| {
+            let _ = Lexer::new(src).lex();
+            let _ = parse_with_errors(src);
+        });
+        assert!(result.is_ok(), "lexing or parsing {:?} panicked", src);
+    }
+
+    #[test]
+    fn crash_corpus_is_handled() {
+        let corpus = [
+            "", "/*", "/* /* */", "\"", "\"\\", "'", "'\\u{D800}'", "0x", "r#", "#[cfg(", "fn main() {",
+            "fn main() {\n    s := \"abc;\n}\n", "c := 'ab';",
+        ];
+        for src in corpus {
+            assert_survives(src);
+        }
+    }
+
+    #[test]
+    fn random_input_never_panics() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..2000 {
+            let length = rng.random_range(0..24);
+            let src: String = (0..length)
+                .map(|_| {
+                    if rng.random_bool(0.9) {
+                        FRAGMENTS[rng.random_range(0..FRAGMENTS.len())].to_string()
+                    } else {
+                        rng.random::<char>().to_string()
+                    }
+                })
+                .collect();
+            assert_survives(&src);
+        }
     }
 }
@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+
+use colored::Colorize;
+
+use crate::front::nodes::node::Node;
+use crate::front::semantic::SemanticContext;
+use crate::middle::ir::{IRContext, IRInstruction};
+
+use super::expr::Expr;
+use super::r#type::Type;
+
+/// A braced block of statements, used by functions, branches and loops.
+pub struct Body {
+    pub children: Vec<Box<dyn Node>>,
+}
+
+impl Node for Body {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "Body".blue(), width = indentation);
+        for child in &self.children {
+            child.display(indentation + 4);
+        }
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        ctx.enter_scope();
+        for stmt in &self.children {
+            stmt.analyze(ctx)?;
+        }
+        ctx.exit_scope();
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        self.children.iter().flat_map(|stmt| stmt.ir(ctx)).collect()
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        self.children.iter().map(|child| child.as_ref()).collect()
+    }
+
+    fn diverges(&self) -> bool {
+        self.children.iter().any(|child| child.diverges())
+    }
+}
+
+/// A block used as an expression, `{ stmt; stmt; value }`.
+///
+/// The block evaluates to its trailing expression, or to `void` when every
+/// statement is terminated by `;`. A block without a value that always leaves
+/// early, like `{ ret 0; }`, has type `!`.
+pub struct BlockExpr {
+    pub body: Body,
+    pub value: Option<Expr>,
+    /// The type of `value`, recorded during semantic analysis while the block's locals are in scope.
+    pub value_type: RefCell<Option<Type>>,
+}
+
+impl BlockExpr {
+    pub fn new(body: Body, value: Option<Expr>) -> Self {
+        BlockExpr {
+            body,
+            value,
+            value_type: RefCell::new(None),
+        }
+    }
+
+    pub fn infer_type(&self) -> Result<Type, String> {
+        self.value_type
+            .borrow()
+            .clone()
+            .ok_or_else(|| "Block expression type is unknown before analysis".to_string())
+    }
+}
+
+impl Node for BlockExpr {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "Block".cyan(), width = indentation);
+        for child in &self.body.children {
+            child.display(indentation + 4);
+        }
+        if let Some(value) = &self.value {
+            value.display(indentation + 4);
+        }
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        ctx.enter_scope();
+        for stmt in &self.body.children {
+            stmt.analyze(ctx)?;
+        }
+
+        let value_type = match &self.value {
+            Some(value) => {
+                value.analyze(ctx)?;
+                value.infer_type(ctx)?
+            }
+            None if self.body.diverges() => Type::Never,
+            None => Type::basic("void"),
+        };
+        ctx.exit_scope();
+
+        *self.value_type.borrow_mut() = Some(value_type);
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        // The trailing expression is lowered last, so its temporary holds the block's value.
+        let mut instructions = self.body.ir(ctx);
+        if let Some(value) = &self.value {
+            instructions.extend(value.ir(ctx));
+        }
+        instructions
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        let mut children = self.body.children();
+        if let Some(value) = &self.value {
+            children.push(value);
+        }
+        children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{analyze, run};
+
+    #[test]
+    fn block_takes_the_value_and_type_of_its_trailing_expression() {
+        assert_eq!(run("fn main() -> i32 { x := { t := 6 * 7; t }; ret x; }"), 42);
+        assert!(analyze("fn main() { b: bool = { c := 1; c == 1 }; }").is_ok());
+        assert!(analyze("fn main() { b: bool = { 1 }; }").is_err());
+    }
+
+    #[test]
+    fn blocks_accept_every_statement_a_body_does() {
+        assert_eq!(run("fn main() -> i32 { x := { const K: i32 = 2; K * 21 }; ret x; }"), 42);
+        let error = analyze("fn main() { x := { const K: i32 = 2; K = 3; K }; }").err().unwrap();
+        assert_eq!(error, "Cannot assign to constant 'K'");
+    }
+
+    #[test]
+    fn block_ending_in_a_statement_is_void() {
+        let error = analyze("fn main() { x: i32 = { 1; }; }").err().unwrap();
+        assert!(error.contains("void"), "{}", error);
+    }
+
+    #[test]
+    fn block_locals_are_scoped_to_the_block() {
+        let error = analyze("fn main() -> i32 { x := { t := 1; t }; ret t; }").err().unwrap();
+        assert!(error.starts_with("Undefined"), "{}", error);
+    }
+}
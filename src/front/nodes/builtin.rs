@@ -0,0 +1,142 @@
+use colored::Colorize;
+
+use crate::front::nodes::node::Node;
+use crate::front::semantic::SemanticContext;
+use crate::front::token::Position;
+use crate::middle::ir::{Constant, IRContext, IRInstruction};
+
+use super::expr::Expr;
+use super::r#type::Type;
+
+/// The signature of a compiler intrinsic callable as `@name(...)`.
+pub struct BuiltinSignature {
+    pub name: &'static str,
+    pub parameters: &'static [&'static str],
+    pub return_type: &'static str,
+}
+
+/// Every intrinsic known to the compiler. Builtins live outside the symbol
+/// table, so they never collide with user-defined names.
+pub const BUILTINS: &[BuiltinSignature] = &[
+    // Aborts the program immediately.
+    BuiltinSignature {
+        name: "trap",
+        parameters: &[],
+        return_type: "!",
+    },
+];
+
+pub fn lookup_builtin(name: &str) -> Option<&'static BuiltinSignature> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// A call to a compiler intrinsic, e.g. `@trap()`.
+pub struct BuiltinCall {
+    pub name: String,
+    pub arguments: Vec<Expr>,
+    pub position: Position,
+}
+
+impl BuiltinCall {
+    pub fn signature(&self) -> Result<&'static BuiltinSignature, String> {
+        lookup_builtin(&self.name).ok_or_else(|| format!("Unknown builtin '@{}'", self.name))
+    }
+}
+
+impl Node for BuiltinCall {
+    fn display(&self, indentation: usize) {
+        println!(
+            "{:>width$}└───[ {}: `@{}`",
+            "",
+            "Builtin".green(),
+            self.name,
+            width = indentation
+        );
+        for expr in &self.arguments {
+            expr.display(indentation + 4);
+        }
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        let signature = self.signature()?;
+
+        if self.arguments.len() != signature.parameters.len() {
+            return Err(format!(
+                "Builtin '@{}' takes {} argument(s) but {} were supplied",
+                self.name,
+                signature.parameters.len(),
+                self.arguments.len()
+            ));
+        }
+
+        for (argument, parameter) in self.arguments.iter().zip(signature.parameters) {
+            argument.analyze(ctx)?;
+            let found = argument.infer_type(ctx)?;
+            let expected = Type::basic(parameter);
+            if found != expected {
+                return Err(format!(
+                    "Builtin '@{}' expects an argument of type {}, found {}",
+                    self.name, expected, found
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        match self.name.as_str() {
+            "trap" => {
+                let message = format!("trap at line {}", self.position.line);
+                vec![IRInstruction::Trap {
+                    location: ctx.intern_constant(Constant::Str(message)),
+                }]
+            }
+            _ => unreachable!("Unknown builtin '@{}' should be rejected during analysis", self.name),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        self.arguments.iter().map(|arg| arg as &dyn Node).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::back::target::Target;
+    use crate::middle::ir::{Constant, IRInstruction};
+    use crate::testing::{analyze, lower, lower_for};
+
+    #[test]
+    fn trap_names_its_source_line() {
+        let (ir, ctx) = lower_for("fn main() {\n    x := 1;\n    @trap();\n}", Target::X86_64);
+        let location = ir
+            .iter()
+            .find_map(|inst| match inst {
+                IRInstruction::Trap { location } => Some(location),
+                _ => None,
+            })
+            .unwrap();
+        let message = ctx.constants().iter().find(|(label, _)| label == location).map(|(_, c)| c);
+        assert_eq!(message, Some(&Constant::Str("trap at line 3".to_string())));
+    }
+
+    #[test]
+    fn trap_lowers_to_a_trap_instruction() {
+        let ir = lower("fn main() { @trap(); }");
+        assert!(ir.iter().any(|inst| matches!(inst, IRInstruction::Trap { .. })));
+    }
+
+    #[test]
+    fn unknown_builtin_is_an_error() {
+        assert_eq!(analyze("fn main() { @foo(); }").err().unwrap(), "Unknown builtin '@foo'");
+    }
+
+    #[test]
+    fn builtins_do_not_collide_with_user_functions() {
+        let ir = lower("fn trap() {} fn main() { trap(); @trap(); }");
+        assert!(ir
+            .iter()
+            .any(|inst| matches!(inst, IRInstruction::Call { function, .. } if function == "trap")));
+        assert!(ir.iter().any(|inst| matches!(inst, IRInstruction::Trap { .. })));
+    }
+}
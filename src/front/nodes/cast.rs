@@ -0,0 +1,186 @@
+use std::cell::RefCell;
+
+use colored::Colorize;
+
+use crate::front::nodes::node::Node;
+use crate::front::semantic::SemanticContext;
+use crate::middle::ir::{IRContext, IRInstruction};
+
+use super::expr::Expr;
+use super::id::NodeId;
+use super::r#type::{PrimitiveType, Type};
+
+/// An `expr as Type` conversion.
+pub struct Cast {
+    pub id: NodeId,
+    pub expr: Expr,
+    pub target: Type,
+    /// The type of `expr`, recorded during semantic analysis.
+    pub source: RefCell<Option<Type>>,
+}
+
+impl Cast {
+    pub fn new(id: NodeId, expr: Expr, target: Type) -> Self {
+        Cast {
+            id,
+            expr,
+            target,
+            source: RefCell::new(None),
+        }
+    }
+
+    fn is_integer(t: &Type) -> bool {
+        matches!(
+            t,
+            Type::Primitive(
+                PrimitiveType::I32
+                    | PrimitiveType::I64
+                    | PrimitiveType::U32
+                    | PrimitiveType::U64
+                    | PrimitiveType::Usize
+            )
+        )
+    }
+
+    fn is_numeric(t: &Type) -> bool {
+        Self::is_integer(t) || matches!(t, Type::Primitive(PrimitiveType::F32 | PrimitiveType::F64))
+    }
+
+    /// Whether a value of type `from` may be converted to `to` with `as`.
+    fn is_valid(from: &Type, to: &Type) -> bool {
+        if from == to {
+            return true;
+        }
+
+        match (from, to) {
+            // Numbers convert freely between widths, signedness and float/integer.
+            (f, t) if Self::is_numeric(f) && Self::is_numeric(t) => true,
+            // Booleans and characters widen to their integer value.
+            (Type::Primitive(PrimitiveType::Bool | PrimitiveType::Char), t) => Self::is_integer(t),
+            // Integers narrow to a character; the value must be a Unicode scalar value.
+            (f, Type::Primitive(PrimitiveType::Char)) => Self::is_integer(f),
+            _ => false,
+        }
+    }
+}
+
+impl Node for Cast {
+    fn display(&self, indentation: usize) {
+        println!(
+            "{:>width$}└───[ {}: {}",
+            "",
+            "Cast".cyan(),
+            self.target,
+            width = indentation
+        );
+        self.expr.display(indentation + 4);
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        self.expr.analyze(ctx)?;
+
+        let source = self.expr.infer_type(ctx)?;
+        *self.source.borrow_mut() = Some(source.clone());
+
+        if !Self::is_valid(&source, &self.target) {
+            return Err(format!("cannot cast {} to {}", source, self.target));
+        }
+
+        // Constant code points are checked now; others are left to a runtime check.
+        if self.target == Type::basic("char") {
+            let constant = match &self.expr {
+                Expr::Number(n, _) => Some(*n),
+                Expr::FunctionCall { folded, .. } => folded.get().map(|n| n as i64),
+                _ => None,
+            };
+            if let Some(n) = constant {
+                if u32::try_from(n).ok().and_then(char::from_u32).is_none() {
+                    return Err(format!("{} is not a valid char code point", n));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let mut instructions = self.expr.ir(ctx);
+        let src = ctx.get_last_temp();
+
+        let from = self
+            .source
+            .borrow()
+            .as_ref()
+            .expect("Cast source type should be recorded during analysis")
+            .to_string();
+
+        instructions.push(IRInstruction::Convert {
+            dest: ctx.allocate_temp(),
+            src,
+            from,
+            to: self.target.to_string(),
+        });
+        instructions
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.expr]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::middle::ir::IRInstruction;
+    use crate::testing::{analyze, lower, run};
+
+    /// The arithmetic and conversion instructions `src` lowers to, in order.
+    fn operations(src: &str) -> Vec<&'static str> {
+        lower(src)
+            .iter()
+            .filter_map(|inst| match inst {
+                IRInstruction::Add { .. } => Some("add"),
+                IRInstruction::Convert { .. } => Some("convert"),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn as_binds_tighter_than_binary_operators() {
+        // Only `2` is cast, leaving an i32 added to an i64.
+        assert_eq!(
+            analyze("fn main() { a := 1; x := a + 2 as i64; }").err().unwrap(),
+            "Type mismatch in binary expression: i32 and i64"
+        );
+        assert_eq!(operations("fn main() { y: i64 = 5; x := y + 2 as i64; }"), vec!["convert", "add"]);
+
+        // Parentheses cast the whole sum.
+        assert_eq!(operations("fn main() { x: i64 = (1 + 2) as i64; }"), vec!["add", "convert"]);
+        assert_eq!(run("fn main() -> i32 { x := (1 + 2) as i64; ret x as i32; }"), 3);
+    }
+
+    #[test]
+    fn invalid_cast_names_source_and_target() {
+        let error = analyze("fn main() { s := \"hi\"; n := s as i32; }").err().unwrap();
+        assert_eq!(error, "cannot cast str to i32");
+    }
+
+    #[test]
+    fn numeric_casts_keep_the_value() {
+        assert_eq!(run("fn main() -> i32 { x: i64 = 40; ret x as i32 + 2; }"), 42);
+    }
+
+    #[test]
+    fn char_converts_to_its_code_point() {
+        assert_eq!(run("fn main() -> i32 { ret 'A' as u32 as i32; }"), 65);
+        assert_eq!(run("fn main() -> i32 { ret 65 as char as i32; }"), 65);
+    }
+
+    #[test]
+    fn out_of_range_code_point_is_rejected() {
+        assert_eq!(
+            analyze("fn main() { c := 0x110000 as char; }").err().unwrap(),
+            "1114112 is not a valid char code point"
+        );
+        assert!(analyze("fn main() { c := 0xD800 as char; }").is_err());
+    }
+}
@@ -0,0 +1,942 @@
+use std::collections::HashSet;
+
+use colored::Colorize;
+
+use crate::front::nodes::node::Node;
+use crate::front::semantic::SemanticContext;
+use crate::front::token::Position;
+use crate::middle::ir::{Condition, IRContext, IRInstruction};
+
+use super::body::Body;
+use super::expr::Expr;
+use super::r#type::Type;
+use super::variables::Assignment;
+
+/// Checks that the condition of a branch or loop starting at `position` is a `bool`.
+fn check_condition(condition: &Expr, position: &Position, ctx: &mut SemanticContext) -> Result<(), String> {
+    let found = condition.infer_type(ctx)?;
+    if found != Type::basic("bool") {
+        return Err(format!(
+            "Condition must be of type bool, found {} on line {}",
+            found, position.line
+        ));
+    }
+    Ok(())
+}
+
+pub struct IfStatement {
+    pub condition: Expr,
+    pub body: Body,
+    pub else_statement: Option<ElseStatement>,
+    pub position: Position,
+}
+
+impl Node for IfStatement {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "If".red(), width = indentation);
+        self.condition.display(indentation + 4);
+        self.body.display(indentation + 4);
+        if let Some(else_statement) = &self.else_statement {
+            else_statement.display(indentation);
+        }
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        self.condition.analyze(ctx)?;
+        check_condition(&self.condition, &self.position, ctx)?;
+
+        if let Expr::Boolean(value) = self.condition {
+            ctx.lint(
+                format!(
+                    "Condition is always {}, consider removing the branch",
+                    value
+                ),
+                &self.position,
+            );
+        }
+
+        // A variable is only definitely assigned after the `if` when both arms assign it.
+        let before = ctx.uninitialized.clone();
+        self.body.analyze(ctx)?;
+        let after_body = std::mem::replace(&mut ctx.uninitialized, before);
+        if let Some(else_statement) = &self.else_statement {
+            else_statement.analyze(ctx)?;
+        }
+        ctx.uninitialized.extend(after_body);
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        // A constant condition keeps only the taken arm, so no dead branch is emitted.
+        match (&self.condition, &self.else_statement) {
+            (Expr::Boolean(true), _) => self.body.ir(ctx),
+            (Expr::Boolean(false), Some(else_statement)) => else_statement.ir(ctx),
+            (Expr::Boolean(false), None) => Vec::new(),
+            _ => self.branch_ir(ctx),
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        let mut children: Vec<&dyn Node> = vec![&self.condition, &self.body];
+        if let Some(else_statement) = &self.else_statement {
+            children.push(else_statement);
+        }
+        children
+    }
+
+    fn diverges(&self) -> bool {
+        // Without an `else`, a false condition falls through.
+        self.body.diverges() && self.else_statement.as_ref().is_some_and(|e| e.diverges())
+    }
+}
+
+impl IfStatement {
+    /// Lowers a runtime condition to a branch, skipping the `else` labels when there is no `else`.
+    fn branch_ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let mut instructions = self.condition.ir(ctx);
+        let condition = ctx.get_last_temp();
+
+        let then_label = ctx.allocate_label("if_then");
+        let else_label = self.else_statement.as_ref().map(|_| ctx.allocate_label("if_else"));
+        let end_label = ctx.allocate_label("if_end");
+
+        instructions.push(IRInstruction::Branch {
+            condition,
+            true_label: then_label.clone(),
+            false_label: else_label.clone().unwrap_or_else(|| end_label.clone()),
+        });
+
+        instructions.push(IRInstruction::Label(then_label));
+        instructions.extend(self.body.ir(ctx));
+
+        if let (Some(else_statement), Some(else_label)) = (&self.else_statement, else_label) {
+            instructions.push(IRInstruction::Jump {
+                target: end_label.clone(),
+            });
+            instructions.push(IRInstruction::Label(else_label));
+            instructions.extend(else_statement.ir(ctx));
+        }
+
+        instructions.push(IRInstruction::Label(end_label));
+        instructions
+    }
+}
+
+/// The `else` arm of an `if`. An `else if` is stored as a body holding a single `IfStatement`.
+pub struct ElseStatement {
+    pub body: Body,
+}
+
+impl Node for ElseStatement {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "Else".red(), width = indentation);
+        self.body.display(indentation + 4);
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        self.body.analyze(ctx)
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        self.body.ir(ctx)
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.body]
+    }
+
+    fn diverges(&self) -> bool {
+        self.body.diverges()
+    }
+}
+
+/// A C-style `for init; condition; step { body }` loop.
+pub struct ForLoop {
+    pub init: Box<dyn Node>,
+    pub condition: Expr,
+    pub step: Assignment,
+    pub body: Body,
+    pub position: Position,
+}
+
+impl Node for ForLoop {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "For".red(), width = indentation);
+        self.init.display(indentation + 4);
+        self.condition.display(indentation + 4);
+        self.step.display(indentation + 4);
+        self.body.display(indentation + 4);
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        ctx.enter_scope();
+        self.init.analyze(ctx)?;
+        self.condition.analyze(ctx)?;
+        check_condition(&self.condition, &self.position, ctx)?;
+
+        if let Expr::Boolean(false) = self.condition {
+            ctx.lint(
+                "Loop condition is always false, the body never runs".to_string(),
+                &self.position,
+            );
+        }
+
+        // As with `while`, neither the body nor the step is guaranteed to run.
+        let before = ctx.uninitialized.clone();
+        ctx.loop_depth += 1;
+        let body = self.body.analyze(ctx);
+        ctx.loop_depth -= 1;
+        body?;
+        self.step.analyze(ctx)?;
+        ctx.uninitialized.extend(before);
+        ctx.exit_scope();
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        // `continue` jumps to the step, which falls through to the condition at the header.
+        let head_label = ctx.allocate_label("for_head");
+        let body_label = ctx.allocate_label("for_body");
+        let step_label = ctx.allocate_label("for_step");
+        let end_label = ctx.allocate_label("for_end");
+
+        let mut instructions = self.init.ir(ctx);
+        instructions.push(IRInstruction::Label(head_label.clone()));
+        instructions.extend(self.condition.ir(ctx));
+        instructions.push(IRInstruction::Branch {
+            condition: ctx.get_last_temp(),
+            true_label: body_label.clone(),
+            false_label: end_label.clone(),
+        });
+
+        instructions.push(IRInstruction::Label(body_label));
+        ctx.enter_loop(&end_label, &step_label);
+        instructions.extend(self.body.ir(ctx));
+        ctx.exit_loop();
+
+        instructions.push(IRInstruction::Label(step_label));
+        instructions.extend(self.step.ir(ctx));
+        instructions.push(IRInstruction::Jump { target: head_label });
+
+        instructions.push(IRInstruction::Label(end_label));
+        instructions
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![self.init.as_ref(), &self.condition, &self.step, &self.body]
+    }
+}
+
+pub struct WhileLoop {
+    pub condition: Expr,
+    pub body: Body,
+    pub position: Position,
+}
+
+impl Node for WhileLoop {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "While".red(), width = indentation);
+        self.condition.display(indentation + 4);
+        self.body.display(indentation + 4);
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        self.condition.analyze(ctx)?;
+        check_condition(&self.condition, &self.position, ctx)?;
+
+        if let Expr::Boolean(false) = self.condition {
+            ctx.lint(
+                "Loop condition is always false, the body never runs".to_string(),
+                &self.position,
+            );
+        }
+
+        // The body may never run, so assignments inside it don't count afterwards.
+        let before = ctx.uninitialized.clone();
+        ctx.loop_depth += 1;
+        let body = self.body.analyze(ctx);
+        ctx.loop_depth -= 1;
+        body?;
+        ctx.uninitialized.extend(before);
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        // The condition is re-evaluated at the header on every iteration.
+        let head_label = ctx.allocate_label("while_head");
+        let body_label = ctx.allocate_label("while_body");
+        let end_label = ctx.allocate_label("while_end");
+
+        let mut instructions = vec![IRInstruction::Label(head_label.clone())];
+        instructions.extend(self.condition.ir(ctx));
+        instructions.push(IRInstruction::Branch {
+            condition: ctx.get_last_temp(),
+            true_label: body_label.clone(),
+            false_label: end_label.clone(),
+        });
+
+        instructions.push(IRInstruction::Label(body_label));
+        ctx.enter_loop(&end_label, &head_label);
+        instructions.extend(self.body.ir(ctx));
+        ctx.exit_loop();
+        instructions.push(IRInstruction::Jump { target: head_label });
+
+        instructions.push(IRInstruction::Label(end_label));
+        instructions
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.condition, &self.body]
+    }
+}
+
+/// `break;`, leaving the innermost loop.
+pub struct Break {
+    pub position: Position,
+}
+
+impl Node for Break {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "Break".red(), width = indentation);
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        if ctx.loop_depth == 0 {
+            return Err(format!("`break` outside of a loop on line {}", self.position.line));
+        }
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let (break_label, _) = ctx
+            .current_loop()
+            .expect("`break` outside of a loop should be rejected during analysis");
+        vec![IRInstruction::Jump {
+            target: break_label.clone(),
+        }]
+    }
+
+    fn diverges(&self) -> bool {
+        true
+    }
+}
+
+/// `continue;`, starting the next iteration of the innermost loop.
+pub struct Continue {
+    pub position: Position,
+}
+
+impl Node for Continue {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "Continue".red(), width = indentation);
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        if ctx.loop_depth == 0 {
+            return Err(format!("`continue` outside of a loop on line {}", self.position.line));
+        }
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let (_, continue_label) = ctx
+            .current_loop()
+            .expect("`continue` outside of a loop should be rejected during analysis");
+        vec![IRInstruction::Jump {
+            target: continue_label.clone(),
+        }]
+    }
+
+    fn diverges(&self) -> bool {
+        true
+    }
+}
+
+/// Matches with fewer distinct case values than this are lowered as a comparison chain.
+const JUMP_TABLE_MIN_CASES: usize = 4;
+/// The largest table emitted; wider matches are lowered as a comparison chain.
+const JUMP_TABLE_MAX_SPAN: usize = 256;
+
+/// The pattern of a `match` arm over an integer scrutinee.
+pub enum Pattern {
+    Literal(i64),
+    /// An inclusive range `start..=end`.
+    Range(i64, i64),
+    /// `_`, matching any value.
+    Wildcard,
+}
+
+impl Pattern {
+    fn describe(&self) -> String {
+        match self {
+            Pattern::Literal(value) => value.to_string(),
+            Pattern::Range(start, end) => format!("{}..={}", start, end),
+            Pattern::Wildcard => "_".to_string(),
+        }
+    }
+}
+
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Body,
+}
+
+/// `match scrutinee { pattern => { ... }, ... }`, where the arms are tried in order.
+pub struct MatchStatement {
+    pub scrutinee: Expr,
+    pub arms: Vec<MatchArm>,
+    pub position: Position,
+}
+
+impl Node for MatchStatement {
+    fn display(&self, indentation: usize) {
+        println!("{:>width$}└───[ {}", "", "Match".red(), width = indentation);
+        self.scrutinee.display(indentation + 4);
+        for arm in &self.arms {
+            println!(
+                "{:>width$}└───[ {}: `{}`",
+                "",
+                "Arm".red(),
+                arm.pattern.describe(),
+                width = indentation + 4
+            );
+            arm.body.display(indentation + 8);
+        }
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        self.scrutinee.analyze(ctx)?;
+        let scrutinee_type = self.scrutinee.infer_type(ctx)?;
+        if !scrutinee_type.is_integer() {
+            return Err(format!("Only integers can be matched on, found {}", scrutinee_type));
+        }
+
+        let mut exhaustive = false;
+        for arm in &self.arms {
+            if exhaustive {
+                ctx.lint(
+                    format!("Unreachable match arm `{}` after `_`", arm.pattern.describe()),
+                    &self.position,
+                );
+            }
+            let bounds = match arm.pattern {
+                Pattern::Literal(value) => vec![value],
+                Pattern::Range(start, end) if start > end => {
+                    return Err(format!("Empty range pattern {}..={}", start, end));
+                }
+                Pattern::Range(start, end) => vec![start, end],
+                Pattern::Wildcard => {
+                    exhaustive = true;
+                    Vec::new()
+                }
+            };
+            if let Some(value) = bounds.iter().find(|value| !scrutinee_type.holds_constant(**value)) {
+                return Err(format!("Pattern {} does not fit in the matched type {}", value, scrutinee_type));
+            }
+        }
+
+        // Integers have too many values to list, so a catch-all arm is required.
+        if !exhaustive {
+            return Err("Non-exhaustive match: add a `_` arm to cover the remaining values".to_string());
+        }
+
+        // As with `if`, a variable is only definitely assigned afterwards when every arm assigns it.
+        let before = std::mem::take(&mut ctx.uninitialized);
+        let mut after = HashSet::new();
+        for arm in &self.arms {
+            ctx.uninitialized = before.clone();
+            arm.body.analyze(ctx)?;
+            after.extend(ctx.uninitialized.drain());
+        }
+        ctx.uninitialized = after;
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let mut instructions = self.scrutinee.ir(ctx);
+        let scrutinee = ctx.get_last_temp();
+
+        match self.jump_table_cases() {
+            Some((low, table)) => self.jump_table_ir(ctx, &mut instructions, &scrutinee, low, &table),
+            None => self.comparison_chain_ir(ctx, &mut instructions, &scrutinee),
+        }
+        instructions
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        let mut children: Vec<&dyn Node> = vec![&self.scrutinee];
+        for arm in &self.arms {
+            children.push(&arm.body);
+        }
+        children
+    }
+}
+
+impl MatchStatement {
+    /// The arms reachable before the `_` arm, and the `_` arm itself.
+    fn split_wildcard(&self) -> (&[MatchArm], Option<&MatchArm>) {
+        match self.arms.iter().position(|arm| matches!(arm.pattern, Pattern::Wildcard)) {
+            Some(index) => (&self.arms[..index], Some(&self.arms[index])),
+            None => (&self.arms, None),
+        }
+    }
+
+    /// Returns the lowest case value and, for every value from it upwards, the index
+    /// of the arm that handles it, when the cases are dense enough for a jump table.
+    fn jump_table_cases(&self) -> Option<(i64, Vec<Option<usize>>)> {
+        let (arms, _) = self.split_wildcard();
+
+        let mut cases: Vec<(i64, usize)> = Vec::new();
+        for (index, arm) in arms.iter().enumerate() {
+            match arm.pattern {
+                Pattern::Literal(value) => cases.push((value, index)),
+                Pattern::Range(start, end) => {
+                    if end.checked_sub(start)? >= JUMP_TABLE_MAX_SPAN as i64 {
+                        return None;
+                    }
+                    cases.extend((start..=end).map(|value| (value, index)));
+                }
+                Pattern::Wildcard => unreachable!("arms end before the first `_`"),
+            }
+        }
+
+        let low = cases.iter().map(|(value, _)| *value).min()?;
+        let high = cases.iter().map(|(value, _)| *value).max()?;
+        let span = usize::try_from(high.checked_sub(low)?).ok()? + 1;
+        if span > JUMP_TABLE_MAX_SPAN {
+            return None;
+        }
+
+        // The first arm to mention a value wins, as in the comparison chain.
+        let mut table = vec![None; span];
+        for (value, index) in cases {
+            table[(value - low) as usize].get_or_insert(index);
+        }
+
+        // Dense means at least half of the table slots hold a case.
+        let distinct = table.iter().filter(|slot| slot.is_some()).count();
+        if distinct < JUMP_TABLE_MIN_CASES || distinct * 2 < span {
+            return None;
+        }
+        Some((low, table))
+    }
+
+    /// Lowers the match to a single indexed jump over the values `low..low + table.len()`.
+    fn jump_table_ir(
+        &self,
+        ctx: &mut IRContext,
+        instructions: &mut Vec<IRInstruction>,
+        scrutinee: &str,
+        low: i64,
+        table: &[Option<usize>],
+    ) {
+        let (arms, wildcard) = self.split_wildcard();
+
+        // Rebase the scrutinee so the lowest case is index 0.
+        let index = if low == 0 {
+            scrutinee.to_string()
+        } else {
+            let offset = ctx.allocate_temp();
+            instructions.push(IRInstruction::Load {
+                dest: offset.clone(),
+                src: low.to_string(),
+            });
+            let index = ctx.allocate_temp();
+            instructions.push(IRInstruction::Sub {
+                dest: index.clone(),
+                lhs: scrutinee.to_string(),
+                rhs: offset,
+            });
+            index
+        };
+
+        let end_label = ctx.allocate_label("match_end");
+        let default_label = ctx.allocate_label("match_default");
+        let arm_labels: Vec<String> = arms.iter().map(|_| ctx.allocate_label("match_arm")).collect();
+
+        instructions.push(IRInstruction::JumpTable {
+            index,
+            targets: table
+                .iter()
+                .map(|slot| match slot {
+                    Some(arm) => arm_labels[*arm].clone(),
+                    None => default_label.clone(),
+                })
+                .collect(),
+            default: default_label.clone(),
+        });
+
+        for (arm, label) in arms.iter().zip(arm_labels) {
+            instructions.push(IRInstruction::Label(label));
+            instructions.extend(arm.body.ir(ctx));
+            instructions.push(IRInstruction::Jump {
+                target: end_label.clone(),
+            });
+        }
+
+        instructions.push(IRInstruction::Label(default_label));
+        if let Some(wildcard) = wildcard {
+            instructions.extend(wildcard.body.ir(ctx));
+        }
+        instructions.push(IRInstruction::Label(end_label));
+    }
+
+    /// Lowers the match to a chain of comparisons, tried in source order.
+    fn comparison_chain_ir(&self, ctx: &mut IRContext, instructions: &mut Vec<IRInstruction>, scrutinee: &str) {
+        let end_label = ctx.allocate_label("match_end");
+
+        for arm in &self.arms {
+            let condition = match arm.pattern {
+                Pattern::Wildcard => None,
+                Pattern::Literal(value) => {
+                    Some(Self::compare(ctx, instructions, scrutinee, Condition::Equal, value))
+                }
+                Pattern::Range(start, end) => {
+                    let lhs = Self::compare(ctx, instructions, scrutinee, Condition::GreaterEqual, start);
+                    let rhs = Self::compare(ctx, instructions, scrutinee, Condition::LessEqual, end);
+                    let dest = ctx.allocate_temp();
+                    instructions.push(IRInstruction::And { dest: dest.clone(), lhs, rhs });
+                    Some(dest)
+                }
+            };
+
+            let Some(condition) = condition else {
+                // Arms after `_` can never run.
+                instructions.extend(arm.body.ir(ctx));
+                break;
+            };
+
+            let arm_label = ctx.allocate_label("match_arm");
+            let next_label = ctx.allocate_label("match_next");
+            instructions.push(IRInstruction::Branch {
+                condition,
+                true_label: arm_label.clone(),
+                false_label: next_label.clone(),
+            });
+            instructions.push(IRInstruction::Label(arm_label));
+            instructions.extend(arm.body.ir(ctx));
+            instructions.push(IRInstruction::Jump {
+                target: end_label.clone(),
+            });
+            instructions.push(IRInstruction::Label(next_label));
+        }
+
+        instructions.push(IRInstruction::Label(end_label));
+    }
+    /// Emits `scrutinee <condition> value` and returns the temporary holding the result.
+    fn compare(
+        ctx: &mut IRContext,
+        instructions: &mut Vec<IRInstruction>,
+        scrutinee: &str,
+        condition: Condition,
+        value: i64,
+    ) -> String {
+        let constant = ctx.allocate_temp();
+        instructions.push(IRInstruction::Load {
+            dest: constant.clone(),
+            src: value.to_string(),
+        });
+        let dest = ctx.allocate_temp();
+        instructions.push(IRInstruction::Cmp {
+            dest: dest.clone(),
+            condition,
+            lhs: scrutinee.to_string(),
+            rhs: constant,
+        });
+        dest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::middle::ir::IRInstruction;
+    use crate::testing::{analyze, lints, lower, run};
+
+    /// `main` matching `n` against a literal, a range and a wildcard arm.
+    fn classify(n: &str) -> String {
+        format!(
+            "fn main() -> i32 {{
+                n := {};
+                r := 0;
+                match n {{ 0 => {{ r = 10; }}, 1..=5 => {{ r = 20; }}, _ => {{ r = 30; }} }}
+                ret r;
+            }}",
+            n
+        )
+    }
+
+    #[test]
+    fn integer_match_selects_the_matching_arm() {
+        assert_eq!(run(&classify("0")), 10);
+        assert_eq!(run(&classify("1")), 20);
+        assert_eq!(run(&classify("5")), 20);
+        assert_eq!(run(&classify("6")), 30);
+        assert_eq!(run(&classify("0 - 1")), 30);
+    }
+
+    /// Whether `main` matching on `arms` (followed by a wildcard) lowers to a jump table.
+    fn uses_jump_table(arms: &str) -> bool {
+        let src = format!("fn main() {{ n := 3; r := 0; match n {{ {} _ => {{ r = 9; }} }} }}", arms);
+        lower(&src).iter().any(|inst| matches!(inst, IRInstruction::JumpTable { .. }))
+    }
+
+    #[test]
+    fn dense_matches_use_a_jump_table() {
+        let three = "0 => { r = 1; } 1 => { r = 2; } 2 => { r = 3; }";
+        let four = "0 => { r = 1; } 1 => { r = 2; } 2 => { r = 3; } 3 => { r = 4; }";
+        let sparse = "0 => { r = 1; } 10 => { r = 2; } 20 => { r = 3; } 30 => { r = 4; }";
+        assert!(!uses_jump_table(three));
+        assert!(uses_jump_table(four));
+        assert!(uses_jump_table("1..=4 => { r = 1; }"));
+        assert!(!uses_jump_table(sparse));
+        assert!(!uses_jump_table("0..=1000 => { r = 1; }"));
+    }
+
+    #[test]
+    fn jump_table_selects_the_matching_arm() {
+        let arms = "0 => { r = 1; } 1 => { r = 2; } 2..=3 => { r = 3; }";
+        for n in ["0", "1", "3", "4", "0 - 1"] {
+            let src = format!("fn main() -> i32 {{ n := {}; r := 0; match n {{ {} _ => {{ r = 9; }} }} ret r; }}", n, arms);
+            let expected = match n {
+                "0" => 1,
+                "1" => 2,
+                "3" => 3,
+                _ => 9,
+            };
+            assert_eq!(run(&src), expected, "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn invalid_matches_are_rejected() {
+        assert_eq!(
+            analyze("fn main() { n := 1; match n { 0 => {} 1..=5 => {} } }").err().unwrap(),
+            "Non-exhaustive match: add a `_` arm to cover the remaining values"
+        );
+        assert_eq!(
+            analyze("fn main() { n := 1; match n { 5..=1 => {} _ => {} } }").err().unwrap(),
+            "Empty range pattern 5..=1"
+        );
+        assert_eq!(
+            analyze("fn main() { b := true; match b { _ => {} } }").err().unwrap(),
+            "Only integers can be matched on, found bool"
+        );
+    }
+
+    /// The functions called by the lowered `src`, in order.
+    fn calls(src: &str) -> Vec<String> {
+        lower(src)
+            .into_iter()
+            .filter_map(|inst| match inst {
+                IRInstruction::Call { function, .. } => Some(function),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The labels defined by the lowered `src`, in order.
+    fn labels(src: &str) -> Vec<String> {
+        lower(src)
+            .into_iter()
+            .filter_map(|inst| match inst {
+                IRInstruction::Label(label) => Some(label),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn labels_name_their_function_and_construct() {
+        let src = "fn check() { c := true; if c { x := 1; } else { x := 2; } }
+                   fn main() { c := true; while c { break; } }";
+        assert_eq!(
+            labels(src),
+            vec![
+                ".Lcheck_if_then_1",
+                ".Lcheck_if_else_2",
+                ".Lcheck_if_end_3",
+                ".Lmain_while_head_1",
+                ".Lmain_while_body_2",
+                ".Lmain_while_end_3",
+            ]
+        );
+    }
+
+    #[test]
+    fn conditions_must_be_booleans() {
+        assert_eq!(
+            analyze("fn main() {\n    if 5 {}\n}").err().unwrap(),
+            "Condition must be of type bool, found i32 on line 2"
+        );
+        assert_eq!(
+            analyze("fn main() { c := 1; if true {} else if c {} }").err().unwrap(),
+            "Condition must be of type bool, found i32 on line 1"
+        );
+        assert!(analyze("fn main() { x := 1; while x {} }").is_err());
+        assert!(analyze("fn main() { for i := 0; i; i = i + 1 {} }").is_err());
+        assert!(analyze("fn main() { a := 1; b := 2; if a == b {} while a < b { break; } }").is_ok());
+    }
+
+    #[test]
+    fn branch_bodies_are_analyzed() {
+        assert_eq!(
+            analyze("fn main() { c := true; if c { y = 1; } }").err().unwrap(),
+            "Assignment to undeclared variable 'y'"
+        );
+        assert_eq!(
+            analyze("fn main() { c := true; if c {} else { z := w; } }").err().unwrap(),
+            "Undefined variable: w"
+        );
+    }
+
+    #[test]
+    fn runtime_if_branches_around_its_body() {
+        let ir = lower("fn main() -> i32 { a := true; if a { ret 1; } ret 0; }");
+        let label = |name: &str| IRInstruction::Label(name.to_string());
+        assert_eq!(
+            ir[2..],
+            [
+                IRInstruction::LoadVariable { dest: "t2".to_string(), variable: "a".to_string() },
+                IRInstruction::Branch {
+                    condition: "t2".to_string(),
+                    true_label: ".Lmain_if_then_1".to_string(),
+                    false_label: ".Lmain_if_end_2".to_string(),
+                },
+                label(".Lmain_if_then_1"),
+                IRInstruction::Load { dest: "t3".to_string(), src: "1".to_string() },
+                IRInstruction::Ret("t3".to_string()),
+                label(".Lmain_if_end_2"),
+                IRInstruction::Load { dest: "t4".to_string(), src: "0".to_string() },
+                IRInstruction::Ret("t4".to_string()),
+            ]
+        );
+        assert_eq!(run("fn main() -> i32 { a := false; if a { ret 1; } ret 0; }"), 0);
+    }
+
+    #[test]
+    fn while_loops_branch_at_the_header_and_jump_back() {
+        let src = "fn main() -> i32 { a := 0; b := 3; while a < b { a = a + 1; } ret a; }";
+        let skeleton: Vec<IRInstruction> = lower(src)
+            .into_iter()
+            .filter(|inst| {
+                matches!(inst, IRInstruction::Label(_) | IRInstruction::Branch { .. } | IRInstruction::Jump { .. })
+            })
+            .map(|inst| match inst {
+                IRInstruction::Branch { true_label, false_label, .. } => IRInstruction::Branch {
+                    condition: String::new(),
+                    true_label,
+                    false_label,
+                },
+                inst => inst,
+            })
+            .collect();
+        assert_eq!(
+            skeleton,
+            vec![
+                IRInstruction::Label(".Lmain_while_head_1".to_string()),
+                IRInstruction::Branch {
+                    condition: String::new(),
+                    true_label: ".Lmain_while_body_2".to_string(),
+                    false_label: ".Lmain_while_end_3".to_string(),
+                },
+                IRInstruction::Label(".Lmain_while_body_2".to_string()),
+                IRInstruction::Jump { target: ".Lmain_while_head_1".to_string() },
+                IRInstruction::Label(".Lmain_while_end_3".to_string()),
+            ]
+        );
+        assert_eq!(run(src), 3);
+    }
+
+    #[test]
+    fn break_leaves_the_innermost_while_loop() {
+        let src = "fn main() -> i32 {
+            n := 0;
+            outer := true;
+            while outer {
+                while true { n = n + 1; break; }
+                n = n + 10;
+                if n > 30 { break; }
+            }
+            ret n;
+        }";
+        assert_eq!(run(src), 33);
+
+        let ir = lower("fn main() { c := true; while c { break; } }");
+        assert!(ir.contains(&IRInstruction::Jump { target: ".Lmain_while_end_3".to_string() }));
+    }
+
+    #[test]
+    fn break_and_continue_need_a_loop() {
+        assert_eq!(
+            analyze("fn main() {\n    break;\n}").err().unwrap(),
+            "`break` outside of a loop on line 2"
+        );
+        assert_eq!(
+            analyze("fn main() { c := true; if c { continue; } }").err().unwrap(),
+            "`continue` outside of a loop on line 1"
+        );
+    }
+
+    #[test]
+    fn constant_conditions_keep_only_the_taken_arm() {
+        let f = "fn f() {} fn g() {}";
+        assert!(calls(&format!("{} fn main() {{ if false {{ f(); }} }}", f)).is_empty());
+        assert_eq!(calls(&format!("{} fn main() {{ if true {{ f(); }} }}", f)), vec!["f"]);
+        assert_eq!(calls(&format!("{} fn main() {{ if false {{ f(); }} else {{ g(); }} }}", f)), vec!["g"]);
+
+        let ir = lower(&format!("{} fn main() {{ if true {{ f(); }} }}", f));
+        assert!(!ir.iter().any(|inst| matches!(inst, IRInstruction::Branch { .. })));
+    }
+
+    #[test]
+    fn constant_conditions_are_linted() {
+        assert_eq!(
+            lints("fn main() {\n    if true {\n        x := 1;\n    }\n}"),
+            vec![("Condition is always true, consider removing the branch".to_string(), 2)]
+        );
+        assert_eq!(
+            lints("fn main() {\n    if false {\n        x := 1;\n    }\n}"),
+            vec![("Condition is always false, consider removing the branch".to_string(), 2)]
+        );
+        assert_eq!(
+            lints("fn main() {\n    while false {\n        x := 1;\n    }\n}"),
+            vec![("Loop condition is always false, the body never runs".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn non_constant_conditions_are_not_linted() {
+        let src = "fn main() {
+            c := true;
+            if c { x := 1; }
+            while c { break; }
+        }";
+        assert!(lints(src).is_empty());
+    }
+
+    #[test]
+    fn continue_in_a_for_loop_runs_the_step() {
+        let src = "fn main() -> i32 {
+            odd := 0;
+            for i := 0; i < 10; i = i + 1 {
+                if i % 2 == 0 { continue; }
+                odd = odd + 1;
+            }
+            ret odd;
+        }";
+        assert_eq!(run(src), 5);
+    }
+
+    #[test]
+    fn break_leaves_a_for_loop() {
+        let src = "fn main() -> i32 {
+            last := 0;
+            for i := 0; i < 10; i = i + 1 {
+                if i == 3 { break; }
+                last = i;
+            }
+            ret last;
+        }";
+        assert_eq!(run(src), 2);
+    }
+}
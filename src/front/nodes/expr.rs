@@ -1,18 +1,54 @@
+use std::cell::{Cell, RefCell};
+
 use colored::Colorize;
 
 use crate::front::nodes::node::Node;
 use crate::front::nodes::operator::Operator;
 use crate::front::semantic::{SemanticContext, Symbol};
-use crate::middle::ir::{IRContext, IRInstruction};
+use crate::middle::ir::{Condition, Constant, IRContext, IRInstruction, Slot};
 
+use super::body::BlockExpr;
+use super::builtin::BuiltinCall;
+use super::cast::Cast;
+use super::id::NodeId;
 use super::r#type::Type;
 
 pub struct BinaryExpr {
+    pub id: NodeId,
     pub op: Operator,
     pub left: Expr,
     pub right: Expr,
 }
 
+impl BinaryExpr {
+    /// The type both operands share. An integer literal takes on the integer
+    /// type of the other operand when it fits, so `x + 1` keeps the type of `x`.
+    fn operand_type(&self, ctx: &mut SemanticContext) -> Result<Type, String> {
+        let left_type = self.left.infer_type(ctx)?;
+        let right_type = self.right.infer_type(ctx)?;
+
+        let literal = match (&self.left, &self.right) {
+            (Expr::Number(value, _), _) if right_type.is_integer() => Some((*value, &right_type)),
+            (_, Expr::Number(value, _)) if left_type.is_integer() => Some((*value, &left_type)),
+            _ => None,
+        };
+        if let Some((value, other)) = literal {
+            if !other.holds_constant(value) {
+                return Err(format!("Integer literal {} does not fit in type {}", value, other));
+            }
+            return Ok(other.clone());
+        }
+
+        if left_type != right_type {
+            return Err(format!(
+                "Type mismatch in binary expression: {} and {}",
+                left_type, right_type
+            ));
+        }
+        Ok(left_type)
+    }
+}
+
 impl Node for BinaryExpr {
     fn display(&self, indentation: usize) {
         println!(
@@ -30,13 +66,18 @@ impl Node for BinaryExpr {
         self.left.analyze(ctx)?;
         self.right.analyze(ctx)?;
 
-        // Infer types for both operands.
-        let left_type = self.left.get_type(ctx);
-        let right_type = self.right.get_type(ctx);
+        // Both operands must have one type, and a number type for arithmetic and ordering.
+        let left_type = self.operand_type(ctx)?;
+        if self.op.is_numeric() && !left_type.is_numeric() {
+            return Err(format!("Operands of {:?} must be numbers, found {}", self.op, left_type));
+        }
 
-        // Check type compatibility (for example, both must be numbers for arithmetic ops).
-        if left_type != right_type {
-            return Err("Type mismatch in binary expression.".to_string());
+        if matches!(self.op, Operator::Equals | Operator::NotEquals) && !left_type.supports_equality() {
+            return Err(format!("Values of type {} cannot be compared for equality", left_type));
+        }
+
+        if self.op.is_logical() && left_type != Type::basic("bool") {
+            return Err(format!("Operands of {:?} must be of type bool, found {}", self.op, left_type));
         }
 
         // Further operator-specific checks could go here.
@@ -44,32 +85,68 @@ impl Node for BinaryExpr {
     }
 
     fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        if self.op.is_logical() && !(self.left.is_pure() && self.right.is_pure()) {
+            return self.short_circuit_ir(ctx);
+        }
+
         let mut instructions = Vec::new();
 
         // Generate IR for the left operand
         let left_ir = self.left.ir(ctx);
         instructions.extend(left_ir); // Add left operand's instructions
+        let lhs = ctx.get_last_temp(); // The left operand's result
 
         // Generate IR for the right operand
         let right_ir = self.right.ir(ctx);
         instructions.extend(right_ir); // Add right operand's instructions
+        let rhs = ctx.get_last_temp(); // The right operand's result
+
+        // `a <=> b` is `(a > b) - (a < b)`, which is 1, 0 or -1.
+        if let Operator::Compare = self.op {
+            let greater = ctx.allocate_temp();
+            let less = ctx.allocate_temp();
+            instructions.push(IRInstruction::Cmp {
+                dest: greater.clone(),
+                condition: Condition::Greater,
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+            });
+            instructions.push(IRInstruction::Cmp {
+                dest: less.clone(),
+                condition: Condition::Less,
+                lhs,
+                rhs,
+            });
+            instructions.push(IRInstruction::Sub {
+                dest: ctx.allocate_temp(),
+                lhs: greater,
+                rhs: less,
+            });
+            return instructions;
+        }
 
         // Allocate a temporary register for the result of this binary operation
         let dest = ctx.allocate_temp();
 
         // Emit an instruction for the binary operation
         let op_instruction = match self.op {
-            Operator::Plus => IRInstruction::Add {
-                dest: dest.clone(),
-                lhs: ctx.get_last_temp(), // Use the last allocated temp for the left operand
-                rhs: ctx.get_second_last_temp(), // Use the second-to-last allocated temp for the right operand
-            },
-            Operator::Minus => IRInstruction::Sub {
-                dest: dest.clone(),
-                lhs: ctx.get_last_temp(),
-                rhs: ctx.get_second_last_temp(),
-            },
-            // Extend to support more operators (e.g., Multiply, Divide, etc.)
+            Operator::Plus => IRInstruction::Add { dest, lhs, rhs },
+            Operator::Minus => IRInstruction::Sub { dest, lhs, rhs },
+            Operator::Asterisk => IRInstruction::Mul { dest, lhs, rhs },
+            Operator::Fslash => IRInstruction::Div { dest, lhs, rhs },
+            Operator::Percent => IRInstruction::Mod { dest, lhs, rhs },
+            // Both operands are pure here, so evaluating the right one unconditionally is safe.
+            Operator::And => IRInstruction::And { dest, lhs, rhs },
+            Operator::Or => IRInstruction::Or { dest, lhs, rhs },
+            Operator::Equals => IRInstruction::Cmp { dest, condition: Condition::Equal, lhs, rhs },
+            Operator::NotEquals => IRInstruction::Cmp { dest, condition: Condition::NotEqual, lhs, rhs },
+            Operator::Less => IRInstruction::Cmp { dest, condition: Condition::Less, lhs, rhs },
+            Operator::Greater => IRInstruction::Cmp { dest, condition: Condition::Greater, lhs, rhs },
+            Operator::LessEquals => IRInstruction::Cmp { dest, condition: Condition::LessEqual, lhs, rhs },
+            Operator::GreaterEquals => {
+                IRInstruction::Cmp { dest, condition: Condition::GreaterEqual, lhs, rhs }
+            }
+            // Extend to support more operators
             _ => panic!("Unsupported operator in BinaryExpr."),
         };
 
@@ -80,93 +157,244 @@ impl Node for BinaryExpr {
 
         instructions
     }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.left, &self.right]
+    }
+}
+
+impl BinaryExpr {
+    /// Lowers `&&`/`||` so the right operand only runs when the left one doesn't decide the result.
+    fn short_circuit_ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let mut instructions = self.left.ir(ctx);
+        let lhs = ctx.get_last_temp();
+
+        // The left operand is the result unless the right one has to be evaluated.
+        let result = ctx.allocate_temp();
+        instructions.push(IRInstruction::Store {
+            dest: result.clone(),
+            src: lhs.clone(),
+        });
+
+        let construct = match self.op {
+            Operator::And => "and",
+            _ => "or",
+        };
+        let rhs_label = ctx.allocate_label(&format!("{}_rhs", construct));
+        let end_label = ctx.allocate_label(&format!("{}_end", construct));
+        let (true_label, false_label) = match self.op {
+            Operator::And => (rhs_label.clone(), end_label.clone()),
+            _ => (end_label.clone(), rhs_label.clone()),
+        };
+        instructions.push(IRInstruction::Branch {
+            condition: lhs,
+            true_label,
+            false_label,
+        });
+
+        instructions.push(IRInstruction::Label(rhs_label));
+        instructions.extend(self.right.ir(ctx));
+        instructions.push(IRInstruction::Store {
+            dest: result.clone(),
+            src: ctx.get_last_temp(),
+        });
+
+        // Join: reload the result so it is the most recent temporary.
+        instructions.push(IRInstruction::Label(end_label));
+        instructions.push(IRInstruction::Load {
+            dest: ctx.allocate_temp(),
+            src: result,
+        });
+        instructions
+    }
+}
+
+/// The base a number literal was written in, kept so it can be printed back as written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberBase {
+    Decimal,
+    Hexadecimal,
+    Binary,
+    Octal,
+}
+
+impl NumberBase {
+    /// Parses an integer literal such as `42`, `0xFF`, `0b1010` or `0o17`.
+    pub fn parse(literal: &str) -> Option<(i64, NumberBase)> {
+        let (digits, base, radix) = match literal.get(..2) {
+            Some("0x") => (&literal[2..], NumberBase::Hexadecimal, 16),
+            Some("0b") => (&literal[2..], NumberBase::Binary, 2),
+            Some("0o") => (&literal[2..], NumberBase::Octal, 8),
+            _ => (literal, NumberBase::Decimal, 10),
+        };
+        i64::from_str_radix(digits, radix).ok().map(|value| (value, base))
+    }
+
+    /// Formats `value` in this base, including its prefix.
+    pub fn format(&self, value: i64) -> String {
+        match self {
+            NumberBase::Decimal => value.to_string(),
+            NumberBase::Hexadecimal => format!("0x{:X}", value),
+            NumberBase::Binary => format!("0b{:b}", value),
+            NumberBase::Octal => format!("0o{:o}", value),
+        }
+    }
 }
 
 pub enum Expr {
-    Number(i64),
+    Number(i64, NumberBase),
     Character(char),
     String(String),
+    Boolean(bool),
     Binary(Box<BinaryExpr>),
-    Identifier(String),
+    Cast(Box<Cast>),
+    /// `cond ? then : els`
+    Ternary {
+        id: NodeId,
+        cond: Box<Expr>,
+        then: Box<Expr>,
+        els: Box<Expr>,
+    },
+    /// A name that was not in scope while parsing, such as a static.
+    Identifier {
+        id: String,
+        /// The slot the name resolved to during semantic analysis, if it is a variable.
+        slot: RefCell<Option<Slot>>,
+    },
     VariableCall {
         id: String,
         resolved: Option<Symbol>,
+        /// The slot the name resolved to during semantic analysis.
+        slot: RefCell<Option<Slot>>,
     },
     FunctionCall {
+        id: NodeId,
         function: String,
         arguments: Vec<Expr>,
+        /// The value of a `len`/`chars` intrinsic, recorded during semantic analysis.
+        folded: Cell<Option<usize>>,
     },
+    /// `@name(arguments)`
+    Builtin(Box<BuiltinCall>),
+    /// `{ statements; value }`
+    Block(Box<BlockExpr>),
+    /// `(a, b, ...)`, or the unit value `()` when empty.
+    Tuple(Vec<Expr>),
     // etc.
 }
 
 impl Expr {
-    /// A non-fallible version returning the type of the expression.
-    pub fn get_type(&self, ctx: &mut SemanticContext) -> Type {
+    /// Whether evaluating the expression has no side effects, so it may be
+    /// evaluated unconditionally. Calls, builtins and blocks are assumed impure.
+    pub fn is_pure(&self) -> bool {
         match self {
-            Expr::Number(_) => {
-                // By default, we treat literal numbers as i32.
-                Type::basic("i32")
-            }
-            Expr::Character(_) => {
-                Type::basic("char")
-            }
-            Expr::String(_) => {
-                Type::basic("str")
-            }
-            Expr::Binary(bin) => {
-                // For simplicity, we assume that a binary expression is valid and
-                // its type is that of its left side.
-                bin.left.get_type(ctx)
-            }
-            Expr::Identifier(id) => {
-                if let Some(symbol) = ctx.lookup(id) {
-                    match symbol {
-                        Symbol::Variable(t) => t.clone(),
-                        Symbol::Function(func_type) => Type::Function(func_type.clone()),
-                        Symbol::Struct(strct) => Type::Struct(strct.clone()),
-                        // If you have other categories, you could add them here.
-                    }
-                } else {
-                    panic!("Undefined identifier: {}", id);
-                }
-            }
-            Expr::VariableCall { id, resolved: _ } => {
-                if let Some(symbol) = ctx.lookup(id) {
-                    if let Symbol::Variable(var_type) = symbol {
-                        var_type.clone()
-                    } else {
-                        panic!("Identifier `{}` is not a variable", id);
-                    }
-                } else {
-                    panic!("Failed to locate the variable `{}`", id);
-                }
-            }
-            Expr::FunctionCall { function, arguments: _ } => {
-                if let Some(symbol) = ctx.lookup(function) {
-                    // Expect the looked-up symbol to be a function.
-                    if let Symbol::Function(func_type) = symbol {
-                        *func_type.return_type.clone()
-                    } else {
-                        panic!("Identifier `{}` is not a function", function);
-                    }
-                } else {
-                    panic!("Failed to locate the function '{}'", function);
-                }
-            }
+            Expr::Binary(binary) => binary.left.is_pure() && binary.right.is_pure(),
+            Expr::Cast(cast) => cast.expr.is_pure(),
+            Expr::Ternary { cond, then, els, .. } => cond.is_pure() && then.is_pure() && els.is_pure(),
+            Expr::Tuple(elements) => elements.iter().all(Expr::is_pure),
+            Expr::FunctionCall { .. } | Expr::Builtin(_) | Expr::Block(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Evaluates the string intrinsics `len` and `chars` at compile time.
+    ///
+    /// Only a single string literal argument is folded: `len` yields the UTF-8
+    /// byte length and `chars` the number of `char`s. Any other call is left alone.
+    pub fn fold_intrinsic(function: &str, arguments: &[Expr]) -> Option<usize> {
+        match (function, arguments) {
+            ("len", [Expr::String(s)]) => Some(s.len()),
+            ("chars", [Expr::String(s)]) => Some(s.chars().count()),
+            _ => None,
+        }
+    }
+
+    /// Folds a call to an intrinsic, unless the name belongs to a user symbol,
+    /// which shadows the intrinsic even when the function is defined further down.
+    fn intrinsic_value(function: &str, arguments: &[Expr], ctx: &SemanticContext) -> Option<usize> {
+        if ctx.lookup_declared(function).is_some() || ctx.functions.contains(function) {
+            return None;
+        }
+        Self::fold_intrinsic(function, arguments)
+    }
+
+    /// Whether `argument`, of type `found`, may be passed to a parameter of type
+    /// `expected`. Integer literals fit any integer type that holds them; other
+    /// values must match exactly, since calls emit no implicit conversions.
+    fn accepts_argument(argument: &Expr, found: &Type, expected: &Type) -> bool {
+        // Calls to a function whose return type is still being inferred can't be checked.
+        let inferred = Type::Custom("<inferred>".to_string());
+        if *found == inferred {
+            return true;
+        }
+        // A literal is checked against its range even when it has the parameter's type by default.
+        match argument {
+            Expr::Number(value, _) => expected.holds_constant(*value),
+            _ => found == expected || *found == Type::Never,
+        }
+    }
+
+    /// The id of a composite expression, whose type is worth caching.
+    fn node_id(&self) -> Option<NodeId> {
+        match self {
+            Expr::Binary(binary) => Some(binary.id),
+            Expr::Cast(cast) => Some(cast.id),
+            Expr::Ternary { id, .. } | Expr::FunctionCall { id, .. } => Some(*id),
+            _ => None,
         }
     }
 
     /// A fallible version that returns an error string on failure.
+    ///
+    /// Results for composite expressions are memoized by `NodeId` in
+    /// `ctx.type_cache`, so repeated queries are O(1); leaves are cheap to recompute.
     pub fn infer_type(&self, ctx: &mut SemanticContext) -> Result<Type, String> {
+        let Some(id) = self.node_id() else {
+            return self.compute_type(ctx);
+        };
+        if let Some(cached) = ctx.type_cache.get(&id) {
+            ctx.type_cache_hits += 1;
+            return Ok(cached.clone());
+        }
+
+        let inferred = self.compute_type(ctx)?;
+        ctx.type_cache.insert(id, inferred.clone());
+        Ok(inferred)
+    }
+
+    fn compute_type(&self, ctx: &mut SemanticContext) -> Result<Type, String> {
         match self {
-            Expr::Number(_) => Ok(Type::basic("i32")),
+            Expr::Number(..) => Ok(ctx.default_integer.clone()),
             Expr::Character(_) => Ok(Type::basic("char")),
             Expr::String(_) => Ok(Type::basic("str")),
-            Expr::Binary(bin_expr) => bin_expr.left.infer_type(ctx),
-            Expr::Identifier(id) => {
+            Expr::Boolean(_) => Ok(Type::basic("bool")),
+            Expr::Binary(bin_expr) => match bin_expr.op {
+                _ if bin_expr.op.is_comparison() || bin_expr.op.is_logical() => {
+                    Ok(Type::basic("bool"))
+                }
+                Operator::Compare => Ok(Type::basic("i32")),
+                _ => bin_expr.operand_type(ctx),
+            },
+            Expr::Cast(cast) => Ok(cast.target.clone()),
+            Expr::Ternary { then, els, .. } => {
+                let then_type = then.infer_type(ctx)?;
+                let els_type = els.infer_type(ctx)?;
+                // An arm that never produces a value takes on the other arm's type.
+                if then_type == Type::Never {
+                    return Ok(els_type);
+                }
+                if then_type != els_type && els_type != Type::Never {
+                    return Err(format!(
+                        "Type mismatch in ternary expression: {:?} and {:?}",
+                        then_type, els_type
+                    ));
+                }
+                Ok(then_type)
+            }
+            Expr::Identifier { id, .. } => {
                 if let Some(symbol) = ctx.lookup(id) {
                     match symbol {
-                        Symbol::Variable(t) => Ok(t.clone()),
+                        Symbol::Variable(t) | Symbol::Const(t) | Symbol::Static(t) => Ok(t.clone()),
                         Symbol::Function(func_type) => Ok(Type::Function(func_type.clone())),
                         Symbol::Struct(strct) => Ok(Type::Struct(strct.clone())),
                     }
@@ -174,9 +402,9 @@ impl Expr {
                     Err(format!("Undefined identifier: {}", id))
                 }
             }
-            Expr::VariableCall { id, resolved: _ } => {
+            Expr::VariableCall { id, .. } => {
                 if let Some(symbol) = ctx.lookup(id) {
-                    if let Symbol::Variable(var_type) = symbol {
+                    if let Symbol::Variable(var_type) | Symbol::Const(var_type) | Symbol::Static(var_type) = symbol {
                         Ok(var_type.clone())
                     } else {
                         Err(format!("Identifier '{}' is not a function", id))
@@ -185,7 +413,10 @@ impl Expr {
                     Err(format!("Failed to locate function '{}'", id))
                 }
             }
-            Expr::FunctionCall { function, arguments: _ } => {
+            Expr::FunctionCall { function, arguments, .. } => {
+                if Self::intrinsic_value(function, arguments, ctx).is_some() {
+                    return Ok(Type::basic("usize"));
+                }
                 if let Some(symbol) = ctx.lookup(function) {
                     if let Symbol::Function(func_type) = symbol {
                         Ok(*func_type.return_type.clone())
@@ -196,6 +427,14 @@ impl Expr {
                     Err(format!("Failed to locate function '{}'", function))
                 }
             }
+            Expr::Builtin(builtin) => Ok(Type::basic(builtin.signature()?.return_type)),
+            Expr::Block(block) => block.infer_type(),
+            Expr::Tuple(elements) => Ok(Type::Tuple(
+                elements
+                    .iter()
+                    .map(|element| element.infer_type(ctx))
+                    .collect::<Result<_, _>>()?,
+            )),
         }
     }
 }
@@ -203,8 +442,8 @@ impl Expr {
 impl Node for Expr {
     fn display(&self, indentation: usize) {
         match self {
-            Expr::Number(value) => {
-                println!("{:>width$}└───[ `{}`", "", value, width = indentation);
+            Expr::Number(value, base) => {
+                println!("{:>width$}└───[ `{}`", "", base.format(*value), width = indentation);
             }
             Expr::Character(ch) => {
                 println!("{:>width$}└───[ '{}'", "", ch, width = indentation);
@@ -212,11 +451,21 @@ impl Node for Expr {
             Expr::String(str) => {
                 println!("{:>width$}└───[ \"{}\"", "", str.replace("\n", ""), width = indentation);
             }
+            Expr::Boolean(value) => {
+                println!("{:>width$}└───[ `{}`", "", value, width = indentation);
+            }
+            Expr::Ternary { cond, then, els, .. } => {
+                println!("{:>width$}└───[ {}", "", "Ternary".cyan(), width = indentation);
+                cond.display(indentation + 4);
+                then.display(indentation + 4);
+                els.display(indentation + 4);
+            }
             Expr::Binary(binary_expr) => {
                 // println!("{:>width$}└───[ Expr: Binary", "", width = indentation);
                 binary_expr.display(indentation /* + 4 */);
             }
-            Expr::Identifier(id) => {
+            Expr::Cast(cast) => cast.display(indentation),
+            Expr::Identifier { id, .. } => {
                 println!(
                     "{:>width$}└───[ {}: `{}`",
                     "",
@@ -225,7 +474,7 @@ impl Node for Expr {
                     width = indentation
                 );
             }
-            Expr::VariableCall { id, resolved } => {
+            Expr::VariableCall { id, resolved, .. } => {
                 println!(
                     "{:>width$}└───[ {}: `{}` : {:?}",
                     "",
@@ -238,6 +487,7 @@ impl Node for Expr {
             Expr::FunctionCall {
                 function,
                 arguments,
+                ..
             } => {
                 println!(
                     "{:>width$}└───[ {}: `{}`",
@@ -251,12 +501,20 @@ impl Node for Expr {
                     expr.display(indentation + 4);
                 }
             }
+            Expr::Builtin(builtin) => builtin.display(indentation),
+            Expr::Block(block) => block.display(indentation),
+            Expr::Tuple(elements) => {
+                println!("{:>width$}└───[ {}", "", "Tuple".green(), width = indentation);
+                for element in elements {
+                    element.display(indentation + 4);
+                }
+            }
         }
     }
 
     fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
         match self {
-            Expr::Number(_) => {
+            Expr::Number(..) => {
                 // A literal number is always valid.
                 Ok(())
             }
@@ -266,27 +524,53 @@ impl Node for Expr {
             Expr::String(_) => {
                 Ok(())
             }
+            Expr::Boolean(_) => {
+                Ok(())
+            }
             Expr::Binary(bin_expr) => {
                 // Delegate to BinaryExpr's analysis.
                 bin_expr.analyze(ctx)
             }
-            Expr::Identifier(id) => {
+            Expr::Cast(cast) => cast.analyze(ctx),
+            Expr::Ternary { cond, then, els, .. } => {
+                cond.analyze(ctx)?;
+                let cond_type = cond.infer_type(ctx)?;
+                if cond_type != Type::basic("bool") {
+                    return Err(format!(
+                        "Ternary condition must be of type bool, found {:?}",
+                        cond_type
+                    ));
+                }
+
+                then.analyze(ctx)?;
+                els.analyze(ctx)?;
+
+                // Both arms must agree on a single type.
+                self.infer_type(ctx).map(|_| ())
+            }
+            Expr::Identifier { id, slot } => {
                 // Analyze the identifier node (ensures it's defined).
+                if let Some(binding) = ctx.resolve(id) {
+                    if ctx.uninitialized.contains(&binding.slot) {
+                        return Err(format!("Use of possibly uninitialized variable '{}'", id));
+                    }
+                    *slot.borrow_mut() = Some(binding.slot.clone());
+                }
 
                 match ctx.lookup(id) {
                     Some(_s) => Ok(()),
-                    None => {
-                        println!("{:?}", id);
-                        Err(String::from("Identifier not found in hashmap?!"))
-                    }
+                    // Names declared in a scope that has since closed end up here.
+                    None => Err(format!("Undefined variable: {}", id)),
                 }
             }
-            Expr::VariableCall { id, resolved: _ } => {
-                if let Some(symbol) = ctx.lookup(id) {
-                    if let Symbol::Variable(_var_type) = symbol {
-                        // Optionally, you could even update the node with the resolved symbol,
-                        // so later phases have immediate access to things like memory offsets.
-                        // resolved = Some(symbol.clone());
+            Expr::VariableCall { id, resolved: _, slot } => {
+                if let Some(binding) = ctx.resolve(id) {
+                    if let Symbol::Variable(_) | Symbol::Const(_) | Symbol::Static(_) = &binding.symbol {
+                        if ctx.uninitialized.contains(&binding.slot) {
+                            return Err(format!("Use of possibly uninitialized variable '{}'", id));
+                        }
+                        // The slot tells lowering which of the shadowed bindings is read.
+                        *slot.borrow_mut() = Some(binding.slot.clone());
                         Ok(())
                     } else {
                         Err(format!("Identifier '{}' is not a variable", id))
@@ -298,19 +582,76 @@ impl Node for Expr {
             Expr::FunctionCall {
                 function,
                 arguments,
-            } => match ctx.lookup(function) {
-                Some(_s) => Ok(()),
-                None => {
-                    println!("{:?}", function);
-                    Err(String::from("Identifier not found in hashmap?!"))
+                folded,
+                ..
+            } => {
+                if let Some(value) = Self::intrinsic_value(function, arguments, ctx) {
+                    folded.set(Some(value));
+                    return Ok(());
                 }
-            },
+
+                let parameters = match ctx.lookup(function) {
+                    Some(Symbol::Function(signature)) => signature.parameters.clone(),
+                    Some(_) => return Err(format!("Identifier '{}' is not a function", function)),
+                    None => return Err(format!("Call to undefined function '{}'", function)),
+                };
+
+                if arguments.len() != parameters.len() {
+                    return Err(format!(
+                        "Function '{}' takes {} argument(s) but {} were supplied",
+                        function,
+                        parameters.len(),
+                        arguments.len()
+                    ));
+                }
+
+                for (index, (argument, expected)) in arguments.iter().zip(&parameters).enumerate() {
+                    argument.analyze(ctx)?;
+                    let found = argument.infer_type(ctx)?;
+                    if !Self::accepts_argument(argument, &found, expected) {
+                        return Err(match argument {
+                            Expr::Number(value, _) if expected.is_integer() => format!(
+                                "Integer literal {} does not fit in argument {} of '{}' of type {}",
+                                value,
+                                index + 1,
+                                function,
+                                expected
+                            ),
+                            _ => format!(
+                                "Argument {} of '{}' expects type {}, found {}",
+                                index + 1,
+                                function,
+                                expected,
+                                found
+                            ),
+                        });
+                    }
+                }
+                Ok(())
+            }
+            Expr::Builtin(builtin) => builtin.analyze(ctx),
+            Expr::Block(block) => block.analyze(ctx),
+            Expr::Tuple(elements) => {
+                for element in elements {
+                    element.analyze(ctx)?;
+                }
+                Ok(())
+            }
         }
     }
 
     fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
         match self {
-            Expr::Number(value) => {
+            Expr::Number(value, _) => {
+                // Values too wide for an immediate are loaded from the constant pool.
+                if i32::try_from(*value).is_err() {
+                    let label = ctx.intern_constant(Constant::Int(*value));
+                    return vec![IRInstruction::LoadConstant {
+                        dest: ctx.allocate_temp(),
+                        label,
+                    }];
+                }
+
                 // Load the constant into a new temporary register
                 let dest = ctx.allocate_temp();
                 vec![IRInstruction::Load {
@@ -318,33 +659,148 @@ impl Node for Expr {
                     src: value.to_string(),
                 }]
             }
-            Expr::Binary(binary_expr) => {
-                // Delegate to the BinaryExpr's ir() method
-                binary_expr.ir(ctx)
+            Expr::String(value) => {
+                let label = ctx.intern_constant(Constant::Str(value.clone()));
+                vec![IRInstruction::LoadConstant {
+                    dest: ctx.allocate_temp(),
+                    label,
+                }]
+            }
+            Expr::Boolean(value) => {
+                let dest = ctx.allocate_temp();
+                vec![IRInstruction::Load {
+                    dest: dest.clone(),
+                    src: (*value as i64).to_string(),
+                }]
             }
-            Expr::Identifier(id) => {
-                // Reference an identifier
+            Expr::Character(ch) => {
+                // Characters are their Unicode code point.
                 let dest = ctx.allocate_temp();
                 vec![IRInstruction::Load {
                     dest: dest.clone(),
-                    src: id.clone(),
+                    src: (*ch as u32).to_string(),
                 }]
             }
-            Expr::VariableCall { id, resolved } => {
+            Expr::Binary(binary_expr) => {
+                // Delegate to the BinaryExpr's ir() method
+                binary_expr.ir(ctx)
+            }
+            Expr::Cast(cast) => cast.ir(ctx),
+            Expr::Ternary { cond, then, els, .. } => {
+                let mut instructions = cond.ir(ctx);
+                let condition = ctx.get_last_temp();
+
+                // Both arms write into the same result temporary.
+                let result = ctx.allocate_temp();
+                let then_label = ctx.allocate_label("ternary_then");
+                let else_label = ctx.allocate_label("ternary_else");
+                let end_label = ctx.allocate_label("ternary_end");
+
+                instructions.push(IRInstruction::Branch {
+                    condition,
+                    true_label: then_label.clone(),
+                    false_label: else_label.clone(),
+                });
+
+                instructions.push(IRInstruction::Label(then_label));
+                instructions.extend(then.ir(ctx));
+                instructions.push(IRInstruction::Store {
+                    dest: result.clone(),
+                    src: ctx.get_last_temp(),
+                });
+                instructions.push(IRInstruction::Jump {
+                    target: end_label.clone(),
+                });
+
+                instructions.push(IRInstruction::Label(else_label));
+                instructions.extend(els.ir(ctx));
+                instructions.push(IRInstruction::Store {
+                    dest: result.clone(),
+                    src: ctx.get_last_temp(),
+                });
+
+                // Join: reload the result so it is the most recent temporary.
+                instructions.push(IRInstruction::Label(end_label));
+                instructions.push(IRInstruction::Load {
+                    dest: ctx.allocate_temp(),
+                    src: result,
+                });
+                instructions
+            }
+            Expr::Identifier { id, slot } => {
+                // Names unresolved while parsing, such as statics, are variables after analysis.
+                let slot = slot.borrow().clone().unwrap_or_else(|| Slot::Local(id.clone()));
+                vec![slot.load(ctx.allocate_temp())]
+            }
+            Expr::VariableCall { id: _, resolved, slot } => {
                 // Here you would generate the proper IR load instruction.
                 // If `resolved` is set, you can retrieve extra info (e.g. memory location).
                 let symbol = resolved.as_ref().expect("Symbol should be resolved by now");
                 // For example:
-                vec![IRInstruction::LoadVariable {
-                    dest: ctx.allocate_temp(),
-                    variable: id.clone(),
-                    // possibly more fields based on 'symbol'
-                }]
+                let slot = slot.borrow();
+                let slot = slot.as_ref().expect("variables should be resolved during analysis");
+                vec![slot.load(ctx.allocate_temp())]
             },
-            // Expr::FunctionCall { function, arguments }
-            _ => {
-                todo!("[_] Expr .get_type()")
+            Expr::FunctionCall { function, arguments, folded, .. } => {
+                if let Some(value) = folded.get() {
+                    return vec![IRInstruction::Load {
+                        dest: ctx.allocate_temp(),
+                        src: value.to_string(),
+                    }];
+                }
+
+                // Evaluate arguments left to right, remembering where each one landed.
+                let mut instructions = Vec::new();
+                let mut temps = Vec::new();
+                for argument in arguments {
+                    instructions.extend(argument.ir(ctx));
+                    temps.push(ctx.get_last_temp());
+                }
+                instructions.push(IRInstruction::Call {
+                    dest: ctx.allocate_temp(),
+                    function: function.clone(),
+                    arguments: temps,
+                });
+                instructions
+            }
+            Expr::Builtin(builtin) => builtin.ir(ctx),
+            Expr::Block(block) => block.ir(ctx),
+            Expr::Tuple(elements) => {
+                // Tuples have no memory layout yet, so only the elements' effects are lowered.
+                elements.iter().flat_map(|element| element.ir(ctx)).collect()
+            }
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        match self {
+            Expr::Binary(binary_expr) => vec![binary_expr.as_ref()],
+            Expr::Cast(cast) => vec![cast.as_ref()],
+            Expr::Ternary { cond, then, els, .. } => vec![cond.as_ref(), then.as_ref(), els.as_ref()],
+            Expr::FunctionCall { arguments, .. } => {
+                arguments.iter().map(|arg| arg as &dyn Node).collect()
             }
+            Expr::Builtin(builtin) => vec![builtin.as_ref()],
+            Expr::Block(block) => vec![block.as_ref()],
+            Expr::Tuple(elements) => elements.iter().map(|element| element as &dyn Node).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn called_function(&self) -> Option<&str> {
+        match self {
+            Expr::FunctionCall { function, .. } => Some(function),
+            _ => None,
+        }
+    }
+
+    fn diverges(&self) -> bool {
+        match self {
+            Expr::Builtin(builtin) => builtin
+                .signature()
+                .is_ok_and(|signature| signature.return_type == "!"),
+            Expr::Block(block) => block.body.diverges(),
+            _ => false,
         }
     }
 }
@@ -359,17 +815,23 @@ impl Node for ExpressionStatement {
         // Display the underlying expression; you could customize this as needed.
         // For instance:
         match &self.expression {
-            Expr::Number(n) => println!("{:>width$}-> Number({})", "", n, width = indentation + 4),
+            Expr::Number(n, base) => println!("{:>width$}-> Number({})", "", base.format(*n), width = indentation + 4),
             Expr::Character(ch) => println!("{:>width$}-> Character('{}')", "", ch, width = indentation + 4),
             Expr::String(str) => println!("{:>width$}-> String(\"{}\")", "", str, width = indentation + 4),
+            Expr::Boolean(b) => println!("{:>width$}-> Boolean({})", "", b, width = indentation + 4),
+            Expr::Ternary { .. } => self.expression.display(indentation + 4),
             Expr::Binary(bin) => bin.display(indentation + 4),
-            Expr::Identifier(id) => println!(
+            Expr::Cast(cast) => cast.display(indentation + 4),
+            Expr::Builtin(builtin) => builtin.display(indentation + 4),
+            Expr::Block(block) => block.display(indentation + 4),
+            Expr::Tuple(_) => self.expression.display(indentation + 4),
+            Expr::Identifier { id, .. } => println!(
                 "{:>width$}-> Identifier({})",
                 "",
                 id,
                 width = indentation + 4
             ),
-            Expr::VariableCall { id, resolved } => {
+            Expr::VariableCall { id, resolved, .. } => {
                 println!(
                     "{:>width$}└───[ VarCall: `{}` : {:?}",
                     "",
@@ -381,6 +843,7 @@ impl Node for ExpressionStatement {
             Expr::FunctionCall {
                 function,
                 arguments,
+                ..
             } => {
                 println!(
                     "{:>width$}└───[ FnCall: `{}`",
@@ -404,4 +867,205 @@ impl Node for ExpressionStatement {
     fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
         self.expression.ir(ctx)
     }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.expression]
+    }
+
+    fn diverges(&self) -> bool {
+        self.expression.diverges()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NumberBase;
+    use crate::back::target::Target;
+    use crate::middle::ir::IRInstruction;
+    use crate::testing::{analyze, lower, lower_for, run};
+
+    #[test]
+    fn call_arguments_are_checked_against_the_parameters() {
+        let f = "fn f(a: i32, b: bool) -> i32 { ret a; }";
+        assert!(analyze(&format!("{} fn main() {{ x := f(1, true); }}", f)).is_ok());
+        assert_eq!(
+            analyze(&format!("{} fn main() {{ x := f(1); }}", f)).err().unwrap(),
+            "Function 'f' takes 2 argument(s) but 1 were supplied"
+        );
+        assert_eq!(
+            analyze(&format!("{} fn main() {{ x := f(1, 2); }}", f)).err().unwrap(),
+            "Argument 2 of 'f' expects type bool, found i32"
+        );
+        assert_eq!(
+            analyze(&format!("{} fn main() {{ x := f(5000000000, true); }}", f)).err().unwrap(),
+            "Integer literal 5000000000 does not fit in argument 1 of 'f' of type i32"
+        );
+    }
+
+    #[test]
+    fn literal_operands_take_the_other_operands_integer_type() {
+        assert_eq!(run("fn main() -> i32 { x: i64 = 5; y := x + 1; z: i64 = y; ret z as i32; }"), 6);
+        assert_eq!(run("fn main() -> i32 { x: u64 = 5; ret (2 * x > 9) as i32; }"), 1);
+        assert_eq!(
+            analyze("fn main() { x: i64 = 5; y := x + 1; z: i32 = y; }").err().unwrap(),
+            "Cannot assign a value of type i64 to 'z' of type i32; use an explicit `as` cast"
+        );
+        assert_eq!(
+            analyze("fn main() { x: u32 = 5; y := x + 5000000000; }").err().unwrap(),
+            "Integer literal 5000000000 does not fit in type u32"
+        );
+    }
+
+    #[test]
+    fn arithmetic_and_ordering_need_numbers() {
+        assert_eq!(
+            analyze("fn main() { x := true + false; }").err().unwrap(),
+            "Operands of Plus must be numbers, found bool"
+        );
+        assert_eq!(
+            analyze("fn main() { x := \"a\" * \"b\"; }").err().unwrap(),
+            "Operands of Asterisk must be numbers, found str"
+        );
+        assert_eq!(
+            analyze("fn main() { x := true < false; }").err().unwrap(),
+            "Operands of Less must be numbers, found bool"
+        );
+        assert!(analyze("fn main() { x := true == false; y := 'a' != 'b'; }").is_ok());
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(run("fn main() -> i32 { ret (true || false && false) as i32; }"), 1);
+        assert_eq!(run("fn main() -> i32 { ret (false && true || true) as i32; }"), 1);
+        assert_eq!(run("fn main() -> i32 { a := 1; ret (a < 2 && a > 0 || a == 5) as i32; }"), 1);
+        assert_eq!(
+            analyze("fn main() { b := 1 && true; }").err().unwrap(),
+            "Type mismatch in binary expression: i32 and bool"
+        );
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let boom = "fn boom() -> bool { @trap(); ret true; }";
+        assert_eq!(run(&format!("{} fn main() -> i32 {{ ret (false && boom()) as i32; }}", boom)), 0);
+        assert_eq!(run(&format!("{} fn main() -> i32 {{ ret (true || boom()) as i32; }}", boom)), 1);
+    }
+
+    #[test]
+    fn pure_logical_operands_lower_without_branches() {
+        let has_branch = |ir: &[IRInstruction]| ir.iter().any(|inst| matches!(inst, IRInstruction::Branch { .. }));
+        let pure = lower("fn main() { x := 1; y := 2; b := x > 0 && y > 0; }");
+        assert!(pure.iter().any(|inst| matches!(inst, IRInstruction::And { .. })));
+        assert!(!has_branch(&pure));
+
+        let impure = lower("fn f() -> bool { ret true; } fn g() -> bool { ret false; } fn main() { b := f() && g(); }");
+        assert!(has_branch(&impure));
+    }
+
+    #[test]
+    fn three_way_comparison_yields_the_ordering() {
+        assert_eq!(run("fn main() -> i32 { ret 1 <=> 2; }"), -1);
+        assert_eq!(run("fn main() -> i32 { ret 2 <=> 2; }"), 0);
+        assert_eq!(run("fn main() -> i32 { ret 5 <=> 2; }"), 1);
+    }
+
+    #[test]
+    fn three_way_comparison_binds_looser_than_arithmetic() {
+        assert_eq!(run("fn main() -> i32 { ret 2 <=> 1 + 1; }"), 0);
+        assert_eq!(run("fn main() -> i32 { ret 1 + 1 <=> 3 - 2; }"), 1);
+        assert_eq!(run("fn main() -> i32 { ret (1 <=> 2 == 0 - 1) as i32; }"), 1);
+    }
+
+    #[test]
+    fn three_way_comparison_lowers_to_two_compares_and_a_subtraction() {
+        let ir = lower("fn main() { a := 1; b := 2; c := a <=> b; }");
+        let ops: Vec<&str> = ir
+            .iter()
+            .filter_map(|inst| match inst {
+                IRInstruction::Cmp { .. } => Some("cmp"),
+                IRInstruction::Sub { .. } => Some("sub"),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ops, vec!["cmp", "cmp", "sub"]);
+    }
+
+    #[test]
+    fn remainder_truncates_toward_zero() {
+        assert_eq!(run("fn main() -> i32 { ret (0 - 7) % 3; }"), -1);
+        assert_eq!(run("fn main() -> i32 { ret 7 % (0 - 3); }"), 1);
+        assert_eq!(run("fn main() -> i32 { ret (0 - 7) / 2; }"), -3);
+
+        // The remainder is a single target-independent `Mod` until code generation.
+        let src = "fn main() -> i32 { a := 0 - 7; ret a % 3; }";
+        let x86 = lower_for(src, Target::X86_64).0;
+        assert!(x86.iter().any(|inst| matches!(inst, IRInstruction::Mod { .. })));
+        assert_eq!(x86, lower_for(src, Target::Rp2040).0);
+    }
+
+    #[test]
+    fn prefixed_literals_keep_their_base() {
+        for literal in ["42", "0xFF", "0b1010", "0o17"] {
+            let (value, base) = NumberBase::parse(literal).unwrap();
+            assert_eq!(base.format(value), literal);
+        }
+        assert_eq!(NumberBase::parse("0xFF"), Some((255, NumberBase::Hexadecimal)));
+        assert_eq!(NumberBase::parse("0b1010"), Some((10, NumberBase::Binary)));
+        assert_eq!(NumberBase::parse("0o17"), Some((15, NumberBase::Octal)));
+        assert_eq!(NumberBase::parse("0x"), None);
+    }
+
+    #[test]
+    fn prefixed_literals_evaluate_to_their_value() {
+        assert_eq!(run("fn main() -> i32 { ret 0xFF + 0b1 + 0o10; }"), 264);
+
+        // Values wider than an immediate go through the constant pool.
+        let ir = lower("fn main() { x: i64 = 0x100000000; }");
+        assert!(ir.iter().any(|inst| matches!(inst, IRInstruction::LoadConstant { .. })));
+    }
+
+    #[test]
+    fn len_of_a_literal_folds_to_its_byte_length() {
+        assert_eq!(run("fn main() -> i32 { ret len(\"abc\") as i32; }"), 3);
+        assert_eq!(run("fn main() -> i32 { ret len(\"é\") as i32; }"), 2);
+        assert_eq!(run("fn main() -> i32 { ret chars(\"é\") as i32; }"), 1);
+
+        let ir = lower("fn main() { n: usize = len(\"abc\"); }");
+        assert!(!ir.iter().any(|inst| matches!(inst, IRInstruction::Call { .. })));
+    }
+
+    #[test]
+    fn user_function_shadows_the_intrinsic() {
+        let ir = lower(
+            "fn len(s: str) -> usize { ret 7; }
+             fn main() { n := len(\"abc\"); }",
+        );
+        assert!(ir
+            .iter()
+            .any(|inst| matches!(inst, IRInstruction::Call { function, .. } if function == "len")));
+
+        // Defined below the call, `len` still isn't the intrinsic, and it can't be called before its definition.
+        let error = analyze(
+            "fn main() { n := len(\"abc\"); }
+             fn len(s: str) -> usize { ret 7; }",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(error, "Call to undefined function 'len'");
+    }
+
+    #[test]
+    fn ternary_takes_the_type_of_its_arms() {
+        assert_eq!(run("fn main() -> i32 { c := true; ret c ? 1 : 2; }"), 1);
+        assert_eq!(run("fn main() -> i32 { c := false; ret c ? 1 : 2; }"), 2);
+    }
+
+    #[test]
+    fn ternary_arms_of_different_types_are_rejected() {
+        let error = analyze("fn main() { c := true; x := c ? 1 : 'a'; }").err().unwrap();
+        assert!(error.starts_with("Type mismatch in ternary expression"), "{}", error);
+
+        let error = analyze("fn main() { x := 1 ? 1 : 2; }").err().unwrap();
+        assert!(error.starts_with("Ternary condition must be of type bool"), "{}", error);
+    }
 }
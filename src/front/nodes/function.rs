@@ -1,25 +1,40 @@
+use std::cell::RefCell;
+
 use colored::Colorize;
 
 use crate::front::nodes::node::Node;
 use crate::front::semantic::{SemanticContext, Symbol};
-use crate::middle::ir::{IRContext, IRInstruction};
+use crate::front::token::Position;
+use crate::middle::ir::{IRContext, IRInstruction, Slot};
 
+use super::body::Body;
 use super::expr::Expr;
 use super::r#type::{FunctionType, Type};
 
 pub struct FunctionDefinition {
     pub id: String,
+    pub is_public: bool, // Declared with `pub`
     pub parameters: Vec<FunctionParameter>,
     pub return_type: FunctionReturnType,
-    pub body: Box<FunctionBody>,
+    pub where_clause: Option<WhereClause>,
+    pub body: Box<Body>,
+}
+
+/// `where T: Bound + Bound, ...` after a signature. Generics are not implemented,
+/// so the constraints are kept but never checked.
+pub struct WhereClause {
+    /// Each type parameter with its trait bounds.
+    pub constraints: Vec<(String, Vec<String>)>,
+    pub position: Position,
 }
 
 impl Node for FunctionDefinition {
     fn display(&self, indentation: usize) {
         println!(
-            "{:>width$}└───[ {}: `{}`",
+            "{:>width$}└───[ {}: {}`{}`",
             "",
             "FnDef".yellow(),
+            if self.is_public { "pub " } else { "" },
             self.id,
             width = indentation
         );
@@ -28,29 +43,59 @@ impl Node for FunctionDefinition {
             param.display(indentation + 4);
         }
         self.return_type.display(indentation + 4);
+        if let Some(where_clause) = &self.where_clause {
+            for (parameter, bounds) in &where_clause.constraints {
+                println!(
+                    "{:>width$}└───[ {}: `{}: {}`",
+                    "",
+                    "Where".blue(),
+                    parameter,
+                    bounds.join(" + "),
+                    width = indentation + 4
+                );
+            }
+        }
         self.body.display(indentation + 4);
     }
 
     fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
-        // Check if this function name is already defined.
-        if ctx.lookup(&self.id).is_some() {
-            return Err(format!("Function '{}' already declared.", self.id));
-        }
-        // Here, you might want to create a function signature type.
-        // For simplicity, we assume self.return_type can be converted into a Type.
-        ctx.add_symbol(
-            &self.id,
-            Symbol::Function(FunctionType {
-                // Refactor in future
-                parameters: self.parameters.iter().map(|param| param.r#type.clone()).collect(),
-                return_type: Box::new(self.return_type.0.clone()),
-            }),
-        );
+        // An unannotated return type is a placeholder until the body has been analyzed.
+        let inferred = Type::Custom("<inferred>".to_string());
+        let mut signature = FunctionType {
+            // Refactor in future
+            parameters: self.parameters.iter().map(|param| param.r#type.clone()).collect(),
+            return_type: Box::new(self.return_type.0.clone().unwrap_or(inferred)),
+        };
+
+        // Functions are keyed by name alone, so overloading is rejected outright.
+        // Prelude functions may be shadowed.
+        match ctx.lookup_declared(&self.id) {
+            Some(Symbol::Function(existing)) => {
+                return Err(format!(
+                    "Function '{}' is already defined as `{}`; redefinition as `{}` is not allowed (overloading is not supported).",
+                    self.id, existing, signature
+                ));
+            }
+            Some(_) => {
+                return Err(format!("Function '{}' already declared.", self.id));
+            }
+            None => {}
+        }
+
+        if let Some(where_clause) = &self.where_clause {
+            ctx.warnings.push((
+                format!("Unsupported where clause on '{}' is ignored", self.id),
+                where_clause.position.clone(),
+            ));
+        }
+
+        ctx.add_symbol(&self.id, Symbol::Function(signature.clone()));
 
         // Enter a new scope for the function body.
         ctx.enter_scope();
-        // Set the expected return type.
-        ctx.current_function_return = Some(self.return_type.0.clone());
+        // Set the expected return type, or let the first `ret` decide it.
+        ctx.current_function_return = self.return_type.0.clone();
+        ctx.inferring_return = self.return_type.0.is_none();
 
         // First, analyze each parameter.
         for param in &self.parameters {
@@ -61,40 +106,73 @@ impl Node for FunctionDefinition {
         self.body.analyze(ctx)?;
 
         // Exit the function scope and clear the expected return type.
+        let inferred_return = if ctx.inferring_return {
+            // A body without any `ret` returns nothing.
+            Some(ctx.current_function_return.clone().unwrap_or(Type::basic("void")))
+        } else {
+            None
+        };
         ctx.current_function_return = None;
+        ctx.inferring_return = false;
         ctx.exit_scope();
 
+        // The final signature replaces the placeholder in the enclosing scope.
+        if let Some(return_type) = inferred_return {
+            signature.return_type = Box::new(return_type);
+            ctx.add_symbol(&self.id, Symbol::Function(signature));
+        }
+
         Ok(())
     }
 
     fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
         let mut instructions = Vec::new();
+        ctx.enter_function(&self.id);
 
         // instructions.extend(self.id.ir(ctx));
 
-        // Generate IR for parameters
-        for param in &self.parameters {
+        // Copy each incoming argument into its parameter's slot
+        for (index, param) in self.parameters.iter().enumerate() {
+            instructions.push(IRInstruction::LoadArgument {
+                dest: ctx.allocate_temp(),
+                index,
+            });
             instructions.extend(param.ir(ctx));
         }
 
         // Generate IR for body
         instructions.extend(self.body.ir(ctx));
 
-        // Add a return instruction if necessary
-        if !instructions
-            .iter()
-            .any(|instr| matches!(instr, IRInstruction::Ret(_)))
-        {
+        // Add a return instruction if control can fall off the end
+        if !matches!(instructions.last(), Some(IRInstruction::Ret(_))) {
             instructions.push(IRInstruction::Ret("0".to_string()));
         }
 
         instructions
     }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        let mut children: Vec<&dyn Node> = Vec::new();
+        for param in &self.parameters {
+            children.push(param);
+        }
+        children.push(&self.return_type);
+        children.push(self.body.as_ref());
+        children
+    }
+
+    fn defined_function(&self) -> Option<&str> {
+        Some(&self.id)
+    }
 }
 
 pub struct FunctionParameter {
     pub id: String,
     pub r#type: Type,
+    pub position: Position,
+    /// The slot the parameter was given during semantic analysis; it is
+    /// renamed like any local when it shadows a visible binding.
+    pub slot: RefCell<Option<Slot>>,
 }
 
 impl Node for FunctionParameter {
@@ -112,51 +190,25 @@ impl Node for FunctionParameter {
     }
 
     fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
-        /* This is removed for now, later this logic should do this and not the parser
-
-        if ctx.lookup(&self.id).is_some() {
+        if ctx.lookup_current(&self.id).is_some() {
             return Err(format!("Parameter `{}` is already declared.", self.id));
         }
-        // Insert the parameter into the symbol table.
-        ctx.add_symbol(&self.id, Symbol::Variable(self.r#type.clone()));
-        
-        */
-        Ok(())
-    }
-
-    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
-        Vec::new()
-    }
-}
-
-pub struct FunctionBody {
-    pub children: Vec<Box<dyn Node>>,
-}
-
-impl Node for FunctionBody {
-    fn display(&self, indentation: usize) {
-        println!("{:>width$}└───[ {}", "", "FnBody".blue(), width = indentation);
-        for child in &self.children {
-            child.display(indentation + 4);
-        }
-    }
-
-    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
-        ctx.enter_scope();
-        for stmt in &self.children {
-            stmt.analyze(ctx)?;
-        }
-        ctx.exit_scope();
+        // Insert the parameter into the function's scope.
+        *self.slot.borrow_mut() = Some(ctx.declare(&self.id, Symbol::Variable(self.r#type.clone()), &self.position));
         Ok(())
     }
 
+    /// Stores the argument just loaded by the function into the parameter's slot.
     fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
-        Vec::new()
+        let slot = self.slot.borrow();
+        let slot = slot.as_ref().expect("parameters should be resolved during analysis");
+        vec![slot.store(ctx.get_last_temp())]
     }
 }
 
+/// The annotated return type, or `None` when `-> T` is omitted and the type is inferred.
 #[derive(Clone)]
-pub struct FunctionReturnType(pub Type);
+pub struct FunctionReturnType(pub Option<Type>);
 
 impl Node for FunctionReturnType {
     fn display(&self, indentation: usize) {
@@ -164,7 +216,7 @@ impl Node for FunctionReturnType {
             "{:>width$}└───[ {}: {:?}",
             "",
             "FnRetType".blue(),
-            self.0, // .magenta()
+            self.0.clone().unwrap_or(Type::Custom("<inferred>".to_string())), // .magenta()
             width = indentation
         );
 
@@ -180,15 +232,31 @@ impl Node for FunctionReturnType {
     }
 }
 
+/// `ret value;`, or a bare `ret;` leaving a `void` function early.
 pub struct Return {
-    pub value: Expr,
+    pub value: Option<Expr>,
+}
+
+impl Return {
+    /// Analyzes the returned value and gives its type; a bare `ret;` returns `void`.
+    fn value_type(&self, ctx: &mut SemanticContext) -> Result<Type, String> {
+        match &self.value {
+            Some(value) => {
+                value.analyze(ctx)?;
+                value.infer_type(ctx)
+            }
+            None => Ok(Type::basic("void")),
+        }
+    }
 }
 
 impl Node for Return {
     fn display(&self, indentation: usize) {
         println!("{:>width$}└───[ {}:", "", "Return".red(), width = indentation);
 
-        self.value.display(indentation + 4);
+        if let Some(value) = &self.value {
+            value.display(indentation + 4);
+        }
     }
 
     fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
@@ -200,12 +268,37 @@ impl Node for Return {
             // ... self.value.analyze(ctx)
             // Assuming self.expr (or self.value if you update your node) now holds an expression:
             expected_return_type = exp.clone();
+        } else if ctx.inferring_return {
+            // The first `ret` of an unannotated function fixes its return type.
+            ctx.current_function_return = Some(self.value_type(ctx)?);
+            return Ok(());
         } else {
             return Err("Return statement found outside of a function.".to_string());
         }
 
-        let expr_type = self.value.get_type(ctx); // hypothetical method to compute type; you would implement this
+        let expr_type = self.value_type(ctx)?;
+        // Integer literals take on the declared return type when they fit in it.
+        if let Some(Expr::Number(value, _)) = self.value {
+            if expected_return_type.holds_constant(value) {
+                return Ok(());
+            }
+        }
+        if expr_type == Type::Never {
+            return Ok(());
+        }
+        if self.value.is_none() && expected_return_type != expr_type {
+            return Err(format!(
+                "Missing return value in a function returning {}",
+                expected_return_type
+            ));
+        }
         if expr_type != expected_return_type {
+            if ctx.inferring_return {
+                return Err(format!(
+                    "Conflicting return types in a function without a return annotation: {} and {}",
+                    expected_return_type, expr_type
+                ));
+            }
             return Err(format!(
                 "Type mismatch in return statement: expected {:?}, found {:?}",
                 expected_return_type, expr_type
@@ -215,7 +308,103 @@ impl Node for Return {
     }
 
     fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
-        // vec![IRInstruction::Ret(self.value.clone())]
-        Vec::new()
+        match &self.value {
+            Some(value) => {
+                let mut instructions = value.ir(ctx);
+                instructions.push(IRInstruction::Ret(ctx.get_last_temp()));
+                instructions
+            }
+            // Like falling off the end of a function, a bare `ret` returns 0.
+            None => vec![IRInstruction::Ret("0".to_string())],
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        match &self.value {
+            Some(value) => vec![value],
+            None => Vec::new(),
+        }
+    }
+
+    fn diverges(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{analyze, analyze_with_context, run};
+
+    #[test]
+    fn unannotated_return_types_are_inferred() {
+        assert_eq!(run("fn five() { ret 5; } fn main() -> i32 { ret five(); }"), 5);
+        assert_eq!(
+            analyze("fn five() { ret 5; } fn main() { x: bool = five(); }").err().unwrap(),
+            "Cannot assign a value of type i32 to 'x' of type bool; use an explicit `as` cast"
+        );
+    }
+
+    #[test]
+    fn conflicting_inferred_returns_are_an_error() {
+        assert_eq!(
+            analyze("fn f() { c := true; if c { ret 1; } ret false; } fn main() {}").err().unwrap(),
+            "Conflicting return types in a function without a return annotation: i32 and bool"
+        );
+    }
+
+    #[test]
+    fn functions_with_the_same_name_are_rejected() {
+        let error = analyze(
+            "fn print(x: i32) -> i32 { ret x; }
+             fn print(x: str) -> i32 { ret 0; }
+             fn main() {}",
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error,
+            "Function 'print' is already defined as `fn(i32) -> i32`; redefinition as `fn(str) -> i32` is not allowed (overloading is not supported)."
+        );
+    }
+
+    #[test]
+    fn prelude_functions_may_be_redefined() {
+        assert!(analyze("fn print_int(x: i32) {} fn main() { print_int(1); }").is_ok());
+    }
+
+    #[test]
+    fn where_clauses_are_parsed_and_ignored_with_a_warning() {
+        let src = "fn f() -> i32 where T: Copy + Clone, U: Eq { ret 1; }\nfn main() -> i32 { ret f(); }";
+        assert_eq!(run(src), 1);
+
+        let (result, ctx) = analyze_with_context(src);
+        result.unwrap();
+        let warnings: Vec<(&str, usize)> =
+            ctx.warnings.iter().map(|(message, pos)| (message.as_str(), pos.line)).collect();
+        assert_eq!(warnings, vec![("Unsupported where clause on 'f' is ignored", 1)]);
+    }
+
+    #[test]
+    fn bare_ret_leaves_a_void_function_early() {
+        let f = "fn f() -> void { c := true; if c { ret; } x := 1; }";
+        assert_eq!(run(&format!("{} fn main() -> i32 {{ f(); ret 7; }}", f)), 7);
+        assert!(analyze("fn main() { c := true; if c { ret; } }").is_ok());
+        assert_eq!(
+            analyze("fn f() -> i32 {\n    ret;\n}\nfn main() {}").err().unwrap(),
+            "Missing return value in a function returning i32"
+        );
+    }
+
+    #[test]
+    fn arguments_reach_their_parameters() {
+        assert_eq!(run("fn sub(a: i32, b: i32) -> i32 { ret a - b; } fn main() -> i32 { ret sub(9, 2); }"), 7);
+    }
+
+    #[test]
+    fn parameters_shadowing_a_static_or_function_receive_their_argument() {
+        let src = "static n: i32 = 1; fn g(n: i32) -> i32 { ret n + 1; } fn main() -> i32 { ret g(41); }";
+        assert_eq!(run(src), 42);
+        let src = "fn count(count: i32) -> i32 { ret count * 2; } fn main() -> i32 { ret count(4); }";
+        assert_eq!(run(src), 8);
     }
 }
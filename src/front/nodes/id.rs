@@ -1,3 +1,8 @@
+/// Identifies an expression node for caches kept outside the tree, such as
+/// `SemanticContext::type_cache`. Assigned by the parser, unique within a parse.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct NodeId(pub usize);
+
 /*
 // use std::hash::{Hash, Hasher};
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -1,8 +1,13 @@
+pub mod body;
+pub mod builtin;
+pub mod cast;
+pub mod control;
 pub mod expr;
 pub mod id;
 pub mod node;
 pub mod operator;
 pub mod r#type;
+pub mod structs;
 pub mod variables;
 
 pub mod function;
@@ -7,4 +7,42 @@ pub trait Node {
     fn display(&self, indentation: usize);
     fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String>;
     fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction>;
+
+    /// The direct child nodes, in source order, for generic tree walks.
+    fn children(&self) -> Vec<&dyn Node> {
+        Vec::new()
+    }
+
+    /// The name of the function this node defines, if it is a function definition.
+    fn defined_function(&self) -> Option<&str> {
+        None
+    }
+
+    /// The name of the function this node calls, if it is a call expression.
+    fn called_function(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether control never continues past this node, as after `ret`, `break` or `@trap()`.
+    fn diverges(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Node;
+    use crate::testing::parse;
+
+    fn count(node: &dyn Node) -> usize {
+        1 + node.children().into_iter().map(count).sum::<usize>()
+    }
+
+    #[test]
+    fn children_reach_every_node() {
+        let ast = parse("fn main() { x := 1 + 2; }");
+        // The function, its return type, body and declaration, then the
+        // expression, its binary node and both operands.
+        assert_eq!(count(ast.children[0].as_ref()), 8);
+    }
 }
@@ -4,10 +4,48 @@ pub enum Operator {
     Asign,
     Equals,
     NotEquals,
+    Less,
+    Greater,
+    LessEquals,
+    GreaterEquals,
+    /// Three-way comparison `<=>`, yielding `-1`, `0` or `1` as an `i32`.
     Compare,
+    /// Logical `&&` and `||`. Both short-circuit: the right operand is only
+    /// evaluated when the left one does not already decide the result.
+    And,
+    Or,
     Plus,
     Minus,
     Asterisk,
     Fslash,
-    Percent, // Modulus,
+    /// Remainder truncated toward zero, so it takes the sign of the dividend: `-7 % 3 == -1`.
+    Percent,
+}
+
+impl Operator {
+    /// Whether the operator compares its operands and produces a `bool`.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Operator::Equals
+                | Operator::NotEquals
+                | Operator::Less
+                | Operator::Greater
+                | Operator::LessEquals
+                | Operator::GreaterEquals
+        )
+    }
+
+    /// Whether the operator needs numeric operands: arithmetic, ordering and `<=>`.
+    pub fn is_numeric(&self) -> bool {
+        !matches!(
+            self,
+            Operator::Walrus | Operator::Asign | Operator::Equals | Operator::NotEquals | Operator::And | Operator::Or
+        )
+    }
+
+    /// Whether the operator is a short-circuiting `&&` or `||`.
+    pub fn is_logical(&self) -> bool {
+        matches!(self, Operator::And | Operator::Or)
+    }
 }
@@ -0,0 +1,89 @@
+use colored::Colorize;
+
+use crate::front::nodes::node::Node;
+use crate::front::semantic::{SemanticContext, Symbol};
+use crate::middle::ir::{IRContext, IRInstruction};
+
+use super::r#type::StructType;
+
+/// `struct Name { field: type, ... }`
+pub struct StructDefinition {
+    pub definition: StructType,
+    pub is_public: bool, // Declared with `pub`
+}
+
+impl Node for StructDefinition {
+    fn display(&self, indentation: usize) {
+        println!(
+            "{:>width$}└───[ {}: {}`{}`",
+            "",
+            "StructDef".yellow(),
+            if self.is_public { "pub " } else { "" },
+            self.definition.name,
+            width = indentation
+        );
+        for (name, field_type) in &self.definition.fields {
+            println!(
+                "{:>width$}└───[ {}: `{}` : {:?}",
+                "",
+                "Field".blue(),
+                name,
+                field_type,
+                width = indentation + 4
+            );
+        }
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        let name = &self.definition.name;
+        if ctx.lookup_declared(name).is_some() {
+            return Err(format!("Struct '{}' is already declared.", name));
+        }
+
+        for (index, (field, _)) in self.definition.fields.iter().enumerate() {
+            if self.definition.fields[..index].iter().any(|(earlier, _)| earlier == field) {
+                return Err(format!("Duplicate field '{}' in struct '{}'", field, name));
+            }
+        }
+
+        ctx.add_symbol(name, Symbol::Struct(self.definition.clone()));
+        Ok(())
+    }
+
+    fn ir(&self, _ctx: &mut IRContext) -> Vec<IRInstruction> {
+        // A definition only describes a layout; it emits no code.
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::front::nodes::r#type::Type;
+    use crate::front::semantic::Symbol;
+    use crate::testing::{analyze, analyze_with_context};
+
+    #[test]
+    fn struct_definitions_register_their_fields() {
+        let (result, ctx) = analyze_with_context("struct Point { x: i32, y: char } fn main() {}");
+        result.unwrap();
+        let Some(Symbol::Struct(point)) = ctx.lookup("Point") else {
+            panic!("Point is not a struct symbol");
+        };
+        assert_eq!(
+            point.fields,
+            vec![("x".to_string(), Type::basic("i32")), ("y".to_string(), Type::basic("char"))]
+        );
+    }
+
+    #[test]
+    fn duplicate_structs_and_fields_are_errors() {
+        assert_eq!(
+            analyze("struct P { x: i32, x: i32 } fn main() {}").err().unwrap(),
+            "Duplicate field 'x' in struct 'P'"
+        );
+        assert_eq!(
+            analyze("struct P { x: i32 } struct P { y: i32 } fn main() {}").err().unwrap(),
+            "Struct 'P' is already declared."
+        );
+    }
+}
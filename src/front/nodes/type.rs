@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PrimitiveType {
     Void,
@@ -5,6 +7,12 @@ pub enum PrimitiveType {
     I64,
     U32,
     U64,
+    Usize,
+    F32,
+    F64,
+    Bool,
+    Char,
+    Str,
     // You can add more primitives if needed.
 }
 
@@ -29,6 +37,11 @@ pub enum Type {
     Primitive(PrimitiveType),
     Function(FunctionType),
     Struct(StructType),
+    /// `(a, b, ...)`; the empty tuple `()` is the unit type.
+    Tuple(Vec<Type>),
+    /// `!`, the type of expressions that never produce a value, like `@trap()`
+    /// or a block ending in `ret`. It is accepted wherever any type is expected.
+    Never,
     /// A generic or custom type that might be resolved later (for example, a type alias)
     Custom(String),
 }
@@ -41,8 +54,188 @@ impl Type {
             "i64" => Type::Primitive(PrimitiveType::I64),
             "u32" => Type::Primitive(PrimitiveType::U32),
             "u64" => Type::Primitive(PrimitiveType::U64),
+            "usize" => Type::Primitive(PrimitiveType::Usize),
+            "f32" => Type::Primitive(PrimitiveType::F32),
+            "f64" => Type::Primitive(PrimitiveType::F64),
+            "bool" => Type::Primitive(PrimitiveType::Bool),
+            "char" => Type::Primitive(PrimitiveType::Char),
+            "str" => Type::Primitive(PrimitiveType::Str),
             "void" => Type::Primitive(PrimitiveType::Void),
+            "!" => Type::Never,
             _ => Type::Custom(name.to_string()),
         }
     }
+
+    /// Signedness and bit width of the fixed-width integer types.
+    fn integer_layout(&self) -> Option<(bool, u32)> {
+        match self {
+            Type::Primitive(PrimitiveType::I32) => Some((true, 32)),
+            Type::Primitive(PrimitiveType::I64) => Some((true, 64)),
+            Type::Primitive(PrimitiveType::U32) => Some((false, 32)),
+            Type::Primitive(PrimitiveType::U64) => Some((false, 64)),
+            _ => None,
+        }
+    }
+
+    /// Whether this is one of the integer types.
+    pub fn is_integer(&self) -> bool {
+        self.integer_layout().is_some() || *self == Type::Primitive(PrimitiveType::Usize)
+    }
+
+    /// Whether this is an integer or floating-point type, usable with arithmetic
+    /// and ordering operators.
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || matches!(self, Type::Primitive(PrimitiveType::F32 | PrimitiveType::F64))
+    }
+
+    /// Whether every value of `self` is representable in `target`, so the
+    /// conversion may happen implicitly. Narrowing requires an explicit `as`.
+    pub fn widens_to(&self, target: &Type) -> bool {
+        match (self.integer_layout(), target.integer_layout()) {
+            (Some((from_signed, from_bits)), Some((to_signed, to_bits))) => {
+                if from_signed == to_signed {
+                    from_bits < to_bits
+                } else {
+                    // Unsigned fits in a strictly wider signed type; signed never fits in unsigned.
+                    !from_signed && from_bits < to_bits
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether values of this type can be compared with `==` and `!=`.
+    ///
+    /// Primitives other than `void` compare by value and structs compare field by
+    /// field when every field does. Functions have no equality.
+    pub fn supports_equality(&self) -> bool {
+        match self {
+            Type::Primitive(PrimitiveType::Void) => false,
+            Type::Primitive(_) => true,
+            Type::Struct(strct) => strct.fields.iter().all(|(_, field)| field.supports_equality()),
+            Type::Tuple(elements) => elements.iter().all(Type::supports_equality),
+            Type::Never => false,
+            Type::Function(_) => false,
+            // Unresolved types are given the benefit of the doubt.
+            Type::Custom(_) => true,
+        }
+    }
+
+    /// Whether the integer constant `value` fits in this integer type.
+    pub fn holds_constant(&self, value: i64) -> bool {
+        match self {
+            Type::Primitive(PrimitiveType::I32) => i32::try_from(value).is_ok(),
+            Type::Primitive(PrimitiveType::I64) => true,
+            Type::Primitive(PrimitiveType::U32) => u32::try_from(value).is_ok(),
+            Type::Primitive(PrimitiveType::U64 | PrimitiveType::Usize) => value >= 0,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for PrimitiveType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PrimitiveType::Void => "void",
+            PrimitiveType::I32 => "i32",
+            PrimitiveType::I64 => "i64",
+            PrimitiveType::U32 => "u32",
+            PrimitiveType::U64 => "u64",
+            PrimitiveType::Usize => "usize",
+            PrimitiveType::F32 => "f32",
+            PrimitiveType::F64 => "f64",
+            PrimitiveType::Bool => "bool",
+            PrimitiveType::Char => "char",
+            PrimitiveType::Str => "str",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for FunctionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parameters: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+        write!(f, "fn({}) -> {}", parameters.join(", "), self.return_type)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Primitive(primitive) => write!(f, "{}", primitive),
+            Type::Function(function) => write!(f, "{}", function),
+            Type::Struct(strct) => write!(f, "{}", strct.name),
+            Type::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                // A one-element tuple keeps its comma to tell it apart from a grouped type.
+                if elements.len() == 1 {
+                    write!(f, "({},)", elements[0])
+                } else {
+                    write!(f, "({})", elements.join(", "))
+                }
+            }
+            Type::Never => write!(f, "!"),
+            Type::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FunctionType, StructType, Type};
+    use crate::testing::{analyze, run};
+
+    #[test]
+    fn equality_is_defined_for_values_but_not_functions() {
+        let point = |field: Type| {
+            Type::Struct(StructType {
+                name: "P".to_string(),
+                fields: vec![("x".to_string(), Type::basic("i32")), ("f".to_string(), field)],
+            })
+        };
+        let function = Type::Function(FunctionType {
+            parameters: vec![],
+            return_type: Box::new(Type::basic("void")),
+        });
+
+        assert!(Type::basic("i32").supports_equality());
+        assert!(!Type::basic("void").supports_equality());
+        assert!(!function.supports_equality());
+        assert!(point(Type::basic("bool")).supports_equality());
+        assert!(!point(function).supports_equality());
+    }
+
+    #[test]
+    fn comparisons_check_both_operands() {
+        assert_eq!(run("fn main() -> i32 { ret (1 == 1) as i32 + (1 != 1) as i32; }"), 1);
+        assert_eq!(
+            analyze("fn main() { b := 1 == true; }").err().unwrap(),
+            "Type mismatch in binary expression: i32 and bool"
+        );
+        assert_eq!(
+            analyze("fn f() {} fn main() { b := f() == f(); }").err().unwrap(),
+            "Values of type void cannot be compared for equality"
+        );
+    }
+
+    #[test]
+    fn a_diverging_arm_takes_the_other_arms_type() {
+        let src = |c: bool| {
+            format!("fn main() -> i32 {{ c := {}; x: i32 = c ? 1 : {{ ret 7; }}; ret x; }}", c)
+        };
+        assert_eq!(run(&src(true)), 1);
+        assert_eq!(run(&src(false)), 7);
+        assert!(analyze("fn main() { c := true; x: bool = c ? { @trap(); } : false; }").is_ok());
+        assert_eq!(
+            analyze("fn main() { c := true; x: bool = c ? { ret; } : 1; }").err().unwrap(),
+            "Cannot assign a value of type i32 to 'x' of type bool; use an explicit `as` cast"
+        );
+    }
+
+    #[test]
+    fn divergence_is_accepted_in_value_position() {
+        assert_eq!(run("fn main() -> i32 { x: i32 = { ret 3; }; ret x; }"), 3);
+        assert!(analyze("fn main() { s: str = { @trap(); }; }").is_ok());
+        assert!(analyze("fn f(n: i32) {} fn main() { f({ ret; }); }").is_ok());
+    }
 }
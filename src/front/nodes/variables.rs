@@ -1,12 +1,18 @@
+use std::cell::RefCell;
+
 use colored::Colorize;
 
 use crate::front::semantic::{SemanticContext, Symbol};
+use crate::middle::ir::{IRContext, IRInstruction, Slot};
+
+use crate::front::token::Position;
 
 use super::{expr::Expr, node::Node, r#type::Type};
 
 pub struct VariableDeclaration {
     pub id: String, // Variable name.
     pub var_type: Type,
+    pub position: Position,
 }
 
 impl Node for VariableDeclaration {
@@ -21,15 +27,14 @@ impl Node for VariableDeclaration {
         );
     }
     fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
-        /* This is removed for now, later this logic should do this and not the parser
-
-        if ctx.lookup(&self.id).is_some() {
-            return Err(format!("Variable '{}' already declared", self.id));
+        // Shadowing an outer binding is allowed; redeclaring in the same scope is not.
+        if ctx.lookup_current(&self.id).is_some() {
+            return Err(format!("Variable '{}' already declared in this scope", self.id));
         }
-        // Add to symbol table.
-        ctx.add_symbol(&self.id, Symbol::Variable(self.var_type.clone()));
-        
-        */
+        let slot = ctx.declare(&self.id, Symbol::Variable(self.var_type.clone()), &self.position);
+
+        // Reads are rejected until every path has assigned the variable.
+        ctx.uninitialized.insert(slot);
         Ok(())
     }
     fn ir(&self, _ctx: &mut crate::middle::ir::IRContext) -> Vec<crate::middle::ir::IRInstruction> {
@@ -40,6 +45,81 @@ impl Node for VariableDeclaration {
 pub struct Assignment {
     pub lhs: String, // For now, just the variable name.
     pub value: Expr,
+    pub position: Position,
+    /// The `(from, to)` types of an implicit widening, recorded during semantic analysis.
+    pub widening: RefCell<Option<(Type, Type)>>,
+    /// The slot `lhs` resolved to during semantic analysis.
+    pub slot: RefCell<Option<Slot>>,
+}
+
+impl Assignment {
+    pub fn new(lhs: String, value: Expr, position: Position) -> Self {
+        Assignment {
+            lhs,
+            value,
+            position,
+            widening: RefCell::new(None),
+            slot: RefCell::new(None),
+        }
+    }
+
+    /// Analyzes storing `value` into `lhs`, already resolved to type `declared`.
+    fn analyze_store(&self, declared: &Type, ctx: &mut SemanticContext) -> Result<(), String> {
+        self.value.analyze(ctx)?;
+        self.check_coercion(declared, ctx)?;
+        if let Some(slot) = self.slot.borrow().as_ref() {
+            ctx.uninitialized.remove(slot);
+        }
+
+        if let Expr::VariableCall { id, .. } | Expr::Identifier { id, .. } = &self.value {
+            if *id == self.lhs {
+                ctx.lint(
+                    format!("Self-assignment of '{}' has no effect", self.lhs),
+                    &self.position,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `value` may be stored into a variable of type `declared`,
+    /// recording an implicit widening when one is needed.
+    fn check_coercion(&self, declared: &Type, ctx: &mut SemanticContext) -> Result<(), String> {
+        let found = self.value.infer_type(ctx)?;
+
+        // Calls to a function whose return type is still being inferred can't be checked.
+        let inferred = Type::Custom("<inferred>".to_string());
+        if found == inferred || *declared == inferred {
+            return Ok(());
+        }
+
+        // Integer literals take on any integer type that can hold them, checked
+        // even when the literal already has the declared type by default.
+        if let Expr::Number(value, _) = self.value {
+            if declared.holds_constant(value) {
+                return Ok(());
+            }
+            return Err(format!(
+                "Integer literal {} does not fit in '{}' of type {}",
+                value, self.lhs, declared
+            ));
+        }
+
+        // A diverging value such as `{ ret 0; }` never reaches the store.
+        if found == *declared || found == Type::Never {
+            return Ok(());
+        }
+
+        if found.widens_to(declared) {
+            *self.widening.borrow_mut() = Some((found, declared.clone()));
+            return Ok(());
+        }
+
+        Err(format!(
+            "Cannot assign a value of type {} to '{}' of type {}; use an explicit `as` cast",
+            found, self.lhs, declared
+        ))
+    }
 }
 
 impl Node for Assignment {
@@ -55,19 +135,48 @@ impl Node for Assignment {
         self.value.display(indentation + 4);
     }
     fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
-        if ctx.lookup(&self.lhs).is_none() {
+        let Some(binding) = ctx.resolve(&self.lhs) else {
             return Err(format!("Assignment to undeclared variable '{}'", self.lhs));
+        };
+        let declared = match &binding.symbol {
+            Symbol::Variable(declared) | Symbol::Static(declared) => declared.clone(),
+            Symbol::Const(_) => return Err(format!("Cannot assign to constant '{}'", self.lhs)),
+            _ => return Err(format!("Cannot assign to '{}', it is not a variable", self.lhs)),
+        };
+        *self.slot.borrow_mut() = Some(binding.slot.clone());
+
+        self.analyze_store(&declared, ctx)
+    }
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let mut instructions = self.value.ir(ctx);
+
+        if let Some((from, to)) = self.widening.borrow().as_ref() {
+            let src = ctx.get_last_temp();
+            instructions.push(IRInstruction::Convert {
+                dest: ctx.allocate_temp(),
+                src,
+                from: from.to_string(),
+                to: to.to_string(),
+            });
         }
-        Ok(())
+
+        let slot = self.slot.borrow();
+        let slot = slot.as_ref().expect("assignments should be resolved during analysis");
+        instructions.push(slot.store(ctx.get_last_temp()));
+        instructions
     }
-    fn ir(&self, _ctx: &mut crate::middle::ir::IRContext) -> Vec<crate::middle::ir::IRInstruction> {
-        Vec::new()
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.value]
     }
 }
 
 pub struct WalrusDeclaration {
     pub id: String,        // variable name
     pub initializer: Expr, // storing the initializer expression
+    pub position: Position,
+    /// The slot the variable was given during semantic analysis.
+    pub slot: RefCell<Option<Slot>>,
 }
 
 impl Node for WalrusDeclaration {
@@ -81,23 +190,26 @@ impl Node for WalrusDeclaration {
         );
     }
     fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
-        /*
-
-        if ctx.lookup(&self.id).is_some() {
-            return Err(format!("Variable '{}' already declared", self.id));
+        if ctx.lookup_current(&self.id).is_some() {
+            return Err(format!("Variable '{}' already declared in this scope", self.id));
         }
-        // In a later phase, infer the type from initializer.
-        // For now you could postpone type inference or store a placeholder.
-        ctx.add_symbol(
-            &self.id,
-            Symbol::Variable(Type::Custom("<inferred>".to_string()))
-        );
 
-        */
+        // The variable takes the type of its initializer, which cannot refer to it.
+        self.initializer.analyze(ctx)?;
+        let inferred = self.initializer.infer_type(ctx)?;
+        *self.slot.borrow_mut() = Some(ctx.declare(&self.id, Symbol::Variable(inferred), &self.position));
         Ok(())
     }
-    fn ir(&self, _ctx: &mut crate::middle::ir::IRContext) -> Vec<crate::middle::ir::IRInstruction> {
-        Vec::new()
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let mut instructions = self.initializer.ir(ctx);
+        let slot = self.slot.borrow();
+        let slot = slot.as_ref().expect("declarations should be resolved during analysis");
+        instructions.push(slot.store(ctx.get_last_temp()));
+        instructions
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.initializer]
     }
 }
 
@@ -124,10 +236,116 @@ impl Node for DeclarationAssignment {
         // Then check the assignment's lhs is declared.
         self.assignment.analyze(ctx)
     }
-    fn ir(&self, ctx: &mut crate::middle::ir::IRContext) -> Vec<crate::middle::ir::IRInstruction> {
-        // Later: generate IR for both parts.
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        // The declaration itself emits nothing; only the initial store does.
+        self.assignment.ir(ctx)
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.declaration, &self.assignment]
+    }
+}
+
+/// `const id: type = value;`, a binding that cannot be assigned again.
+pub struct ConstDeclaration {
+    pub const_type: Type,
+    pub initializer: Assignment,
+}
+
+impl Node for ConstDeclaration {
+    fn display(&self, indentation: usize) {
+        println!(
+            "{:>width$}└───[ {}: `{}` : {:?}",
+            "",
+            "ConstDecl".red(),
+            self.initializer.lhs,
+            self.const_type,
+            width = indentation
+        );
+        self.initializer.value.display(indentation + 4);
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        let id = &self.initializer.lhs;
+        if ctx.lookup_current(id).is_some() {
+            return Err(format!("Constant '{}' already declared in this scope", id));
+        }
+
+        // The initializer is checked before the name is bound, so it cannot refer to itself.
+        self.initializer.analyze_store(&self.const_type, ctx)?;
+        let slot = ctx.declare(id, Symbol::Const(self.const_type.clone()), &self.initializer.position);
+        *self.initializer.slot.borrow_mut() = Some(slot);
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        self.initializer.ir(ctx)
+    }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.initializer]
+    }
+}
+
+/// `static id: type = value;` at module level, a mutable global initialized
+/// with a literal. It is visible to the items that follow it.
+pub struct StaticDeclaration {
+    pub static_type: Type,
+    pub initializer: Assignment,
+}
+
+impl StaticDeclaration {
+    /// The initial value as stored in the data section.
+    fn initial_value(&self) -> Option<i64> {
+        match self.initializer.value {
+            Expr::Number(value, _) => Some(value),
+            Expr::Boolean(value) => Some(value as i64),
+            Expr::Character(ch) => Some(ch as i64),
+            _ => None,
+        }
+    }
+}
+
+impl Node for StaticDeclaration {
+    fn display(&self, indentation: usize) {
+        println!(
+            "{:>width$}└───[ {}: `{}` : {:?}",
+            "",
+            "StaticDecl".red(),
+            self.initializer.lhs,
+            self.static_type,
+            width = indentation
+        );
+        self.initializer.value.display(indentation + 4);
+    }
+
+    fn analyze(&self, ctx: &mut SemanticContext) -> Result<(), String> {
+        let id = &self.initializer.lhs;
+        if ctx.lookup_current(id).is_some() {
+            return Err(format!("Static '{}' already declared in this scope", id));
+        }
+
+        // The value is laid out before the program runs, so it must be a literal.
+        if self.initial_value().is_none() {
+            return Err(format!("Static '{}' must be initialized with a literal", id));
+        }
+
+        self.initializer.analyze_store(&self.static_type, ctx)?;
+        ctx.declare(id, Symbol::Static(self.static_type.clone()), &self.initializer.position);
+        Ok(())
+    }
+
+    fn ir(&self, ctx: &mut IRContext) -> Vec<IRInstruction> {
+        let value = self
+            .initial_value()
+            .expect("non-literal static initializers should be rejected during analysis");
+        ctx.define_static(&self.initializer.lhs, &self.static_type, value);
         Vec::new()
     }
+
+    fn children(&self) -> Vec<&dyn Node> {
+        vec![&self.initializer.value]
+    }
 }
 
 /* Use later when refactoring for better node control
@@ -139,3 +357,181 @@ pub struct VariableCall {
 }
 
 */
+
+#[cfg(test)]
+mod tests {
+    use crate::middle::ir::IRInstruction;
+    use crate::testing::{analyze, lints, lower, run};
+
+    #[test]
+    fn constants_are_values_that_cannot_be_assigned() {
+        assert_eq!(run("fn main() -> i32 { const n: i32 = 40; ret n + 2; }"), 42);
+        assert_eq!(
+            analyze("fn main() { const n: i32 = 40; n = 1; }").err().unwrap(),
+            "Cannot assign to constant 'n'"
+        );
+    }
+
+    #[test]
+    fn assigned_values_must_match_the_declared_type() {
+        assert_eq!(run("fn main() -> i32 { x: i32 = 1; x = 2; ret x; }"), 2);
+        assert_eq!(
+            analyze("fn main() { x: i32 = 'c'; }").err().unwrap(),
+            "Cannot assign a value of type char to 'x' of type i32; use an explicit `as` cast"
+        );
+        assert_eq!(
+            analyze("fn main() { x := 1; x = true; }").err().unwrap(),
+            "Cannot assign a value of type bool to 'x' of type i32; use an explicit `as` cast"
+        );
+        assert_eq!(
+            analyze("fn main() { y = 1; }").err().unwrap(),
+            "Assignment to undeclared variable 'y'"
+        );
+    }
+
+    #[test]
+    fn walrus_declarations_take_the_initializer_type() {
+        assert_eq!(run("fn main() -> i32 { x := 5; y := x + 1; ret y; }"), 6);
+        assert!(analyze("fn main() { s := \"hi\"; t: str = s; c := 'c'; d: char = c; }").is_ok());
+        assert_eq!(
+            analyze("fn main() { x := 5; y := x > 1; z: i32 = y; }").err().unwrap(),
+            "Cannot assign a value of type bool to 'z' of type i32; use an explicit `as` cast"
+        );
+        assert_eq!(
+            analyze("fn main() { x := 5; x := 6; }").err().unwrap(),
+            "Variable 'x' already declared in this scope"
+        );
+    }
+
+    #[test]
+    fn narrower_integers_widen_on_assignment() {
+        let ir = lower("fn main() { x: i32 = 7; y: i64 = x; }");
+        assert!(ir.iter().any(|inst| matches!(
+            inst,
+            IRInstruction::Convert { from, to, .. } if from == "i32" && to == "i64"
+        )));
+        assert_eq!(run("fn main() -> i32 { x: i32 = 7; y: i64 = x; ret y as i32; }"), 7);
+    }
+
+    #[test]
+    fn narrowing_assignment_needs_a_cast() {
+        assert_eq!(
+            analyze("fn main() { x: i64 = 7; y: i32 = x; }").err().unwrap(),
+            "Cannot assign a value of type i64 to 'y' of type i32; use an explicit `as` cast"
+        );
+        assert!(analyze("fn main() { x: i64 = 7; y: i32 = x as i32; }").is_ok());
+        assert_eq!(
+            analyze("fn main() { y: i32 = 5000000000; }").err().unwrap(),
+            "Integer literal 5000000000 does not fit in 'y' of type i32"
+        );
+    }
+
+    #[test]
+    fn self_assignment_is_linted() {
+        assert_eq!(
+            lints("fn main() {\n    x := 1;\n    x = x;\n}"),
+            vec![("Self-assignment of 'x' has no effect".to_string(), 3)]
+        );
+        assert!(lints("fn main() {\n    x := 1;\n    y := 2;\n    x = y;\n}").is_empty());
+    }
+
+    #[test]
+    fn shadowing_an_outer_local_is_linted() {
+        let src = "fn main() {\n    x := 1;\n    c := true;\n    if c {\n        x := 2;\n    }\n}";
+        assert_eq!(
+            lints(src),
+            vec![("'x' shadows the declaration on line 2 at position 6".to_string(), 5)]
+        );
+    }
+
+    #[test]
+    fn shadowing_a_parameter_is_linted() {
+        let src = "fn f(n: i32) {\n    n: i32 = 2;\n}\nfn main() {}";
+        assert_eq!(
+            lints(src),
+            vec![("'n' shadows the declaration on line 1 at position 7".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn underscore_names_and_sibling_scopes_are_not_linted() {
+        assert!(lints("fn main() {\n    _x := 1;\n    c := true;\n    if c {\n        _x := 2;\n    }\n}").is_empty());
+
+        let siblings = "fn main() {\n    c := true;\n    if c {\n        x := 1;\n    } else {\n        x := 2;\n    }\n}";
+        assert!(lints(siblings).is_empty());
+        // Without `--lint` shadowing is silently allowed.
+        assert!(analyze("fn main() { x := 1; c := true; if c { x := 2; } }").is_ok());
+    }
+
+    #[test]
+    fn block_locals_do_not_leak() {
+        assert_eq!(
+            analyze("fn main() -> i32 { c := true; if c { y := 1; } ret y; }").err().unwrap(),
+            "Undefined variable: y"
+        );
+    }
+
+    #[test]
+    fn shadowing_restores_the_outer_binding() {
+        assert_eq!(run("fn main() -> i32 { x := 1; c := true; if c { x := 2; x = x + 5; } ret x; }"), 1);
+        assert_eq!(run("fn main() -> i32 { x := 1; c := true; if c { x := 2; ret x; } ret x; }"), 2);
+
+        // The shadowing local gets a slot of its own instead of overwriting `x`.
+        let stores: Vec<String> = lower("fn main() { x := 1; c := true; if c { x := 2; } }")
+            .into_iter()
+            .filter_map(|inst| match inst {
+                IRInstruction::Store { dest, .. } => Some(dest),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(stores, vec!["x", "c", "x.1"]);
+    }
+
+    #[test]
+    fn statics_are_shared_between_functions() {
+        let src = "static counter: i32 = 0; fn bump() { counter = counter + 1; } fn main() -> i32 { bump(); bump(); ret counter; }";
+        assert_eq!(run(src), 2);
+
+        let ir = lower(src);
+        assert!(ir.iter().any(|inst| matches!(inst, IRInstruction::StoreGlobal { global, .. } if global == "counter")));
+        assert!(ir.iter().any(|inst| matches!(inst, IRInstruction::LoadGlobal { global, .. } if global == "counter")));
+    }
+
+    #[test]
+    fn locals_shadowing_a_static_leave_it_untouched() {
+        let src = "static g: i32 = 1; fn set() { g := 5; g = 7; } fn main() -> i32 { set(); ret g; }";
+        assert_eq!(run(src), 1);
+        assert!(!lower(src).iter().any(|inst| matches!(inst, IRInstruction::StoreGlobal { .. })));
+    }
+
+    #[test]
+    fn initialization_is_tracked_per_binding() {
+        // The uninitialized inner `x` does not make the outer one unreadable...
+        assert_eq!(run("fn main() -> i32 { x := 1; c := true; if c { x: i32; } ret x; }"), 1);
+        // ...and assigning an inner `x` does not initialize the outer one.
+        assert_eq!(
+            analyze("fn main() -> i32 { x: i32; c := true; if c { x := 2; } ret x; }").err().unwrap(),
+            "Use of possibly uninitialized variable 'x'"
+        );
+    }
+
+    #[test]
+    fn reading_an_unassigned_local_is_an_error() {
+        let error = analyze("fn main() -> i32 { x: i32; ret x; }").err().unwrap();
+        assert_eq!(error, "Use of possibly uninitialized variable 'x'");
+
+        let error = analyze("fn main() -> i32 { c := true; x: i32; if c { x = 1; } ret x; }").err().unwrap();
+        assert_eq!(error, "Use of possibly uninitialized variable 'x'");
+    }
+
+    #[test]
+    fn assignment_on_every_branch_initializes() {
+        let src = "fn main() -> i32 {
+            c := false;
+            x: i32;
+            if c { x = 1; } else { x = 2; }
+            ret x;
+        }";
+        assert_eq!(run(src), 2);
+    }
+}
@@ -1,16 +1,27 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+
 use crate::front::ast::Ast;
+use crate::front::lexer::Lexer;
 use crate::front::token::Token;
 
-use super::nodes::expr::{BinaryExpr, Expr, ExpressionStatement};
-use super::nodes::function::{
-    FunctionBody, FunctionDefinition, FunctionParameter, FunctionReturnType, Return,
+use super::nodes::body::{BlockExpr, Body};
+use super::nodes::builtin::BuiltinCall;
+use super::nodes::cast::Cast;
+use super::nodes::control::{
+    Break, Continue, ElseStatement, ForLoop, IfStatement, MatchArm, MatchStatement, Pattern, WhileLoop,
 };
+use super::nodes::expr::{BinaryExpr, Expr, ExpressionStatement, NumberBase};
+use super::nodes::function::{FunctionDefinition, FunctionParameter, FunctionReturnType, Return, WhereClause};
+use super::nodes::id::NodeId;
 
 use super::nodes::node::Node;
 use super::nodes::operator::Operator;
-use super::nodes::r#type::Type;
+use super::nodes::r#type::{StructType, Type};
+use super::nodes::structs::StructDefinition;
 use super::nodes::variables::{
-    Assignment, DeclarationAssignment, VariableDeclaration, WalrusDeclaration,
+    Assignment, ConstDeclaration, DeclarationAssignment, StaticDeclaration, VariableDeclaration,
+    WalrusDeclaration,
 };
 use super::semantic::{SemanticContext, Symbol};
 use super::token::Position;
@@ -73,7 +84,7 @@ impl fmt::Display for ParserError {
             } => {
                 write!(
                     f,
-                    "Missing token '{}', expected in file: {} on line {} at position {}",
+                    "Missing token {} in file: {} on line {} at position {}",
                     expected, file, position.line, position.index
                 )
             }
@@ -106,44 +117,181 @@ impl fmt::Display for ParserError {
     }
 }
 
-pub struct Parser {
+impl ParserError {
+    /// Where the error occurred, if known.
+    pub fn position(&self) -> Option<&Position> {
+        match self {
+            ParserError::UnexpectedToken { position, .. }
+            | ParserError::MissingToken { position, .. }
+            | ParserError::SyntaxError { position, .. }
+            | ParserError::InvalidParameter { position, .. } => Some(position),
+            ParserError::GenericError(_) => None,
+        }
+    }
+
+    /// The error text without its file and location.
+    pub fn message(&self) -> String {
+        match self {
+            ParserError::UnexpectedToken { token, .. } => format!("Unexpected token '{:?}'", token),
+            ParserError::MissingToken { expected, .. } => format!("Missing token {}", expected),
+            ParserError::SyntaxError { message, .. } => format!("Syntax error: {}", message),
+            ParserError::InvalidParameter { message, .. } => format!("Invalid parameter: {}", message),
+            ParserError::GenericError(message) => message.clone(),
+        }
+    }
+}
+
+/// Maps a type keyword token to its primitive `Type`, or `None` if the token isn't one.
+pub fn token_to_type(token: &Token) -> Option<Type> {
+    let name = match token {
+        Token::I32 => "i32",
+        Token::I64 => "i64",
+        Token::U32 => "u32",
+        Token::U64 => "u64",
+        Token::Usize => "usize",
+        Token::F32 => "f32",
+        Token::F64 => "f64",
+        Token::Char => "char",
+        Token::Str => "str",
+        Token::Bool => "bool",
+        _ => return None,
+    };
+    Some(Type::basic(name))
+}
+
+pub struct Parser<'a> {
     file: String,
-    tokens: Vec<(Token, Position)>,
-    position: usize,
+    /// Where tokens are pulled from on demand.
+    source: Box<dyn Iterator<Item = (Token, Position)> + 'a>,
+    /// Tokens pulled from `source` but not yet consumed; at most two are needed.
+    lookahead: VecDeque<(Token, Position)>,
+    /// Position of the last token pulled, used to place a synthesized `Eof`.
+    last_position: Position,
+    /// Flags passed with `-D name[=value]`, consulted by `#[cfg(...)]`.
+    defines: HashMap<String, Option<String>>,
+    /// Errors in top-level items; parsing resumes with the next item.
+    errors: Vec<ParserError>,
+    /// Number of `NodeId`s handed out so far.
+    node_count: usize,
 }
 
-impl Parser {
+#[cfg(test)]
+impl Parser<'static> {
+    /// Parses an already lexed token vector, which must end in `Token::Eof`.
     pub fn new(file: String, tokens: Vec<(Token, Position)>) -> Self {
+        Parser::from_source(file, Box::new(tokens.into_iter()))
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Parses while lexing, pulling tokens from `lexer` only as they are needed.
+    pub fn streaming(file: String, lexer: &'a mut Lexer<'_>) -> Self {
+        Parser::from_source(file, Box::new(lexer))
+    }
+
+    fn from_source(file: String, source: Box<dyn Iterator<Item = (Token, Position)> + 'a>) -> Self {
         Parser {
             file,
-            tokens: tokens.to_vec(),
-            position: 0,
+            source,
+            lookahead: VecDeque::new(),
+            last_position: Position { line: 0, index: 1 },
+            defines: HashMap::new(),
+            errors: Vec::new(),
+            node_count: 0,
         }
     }
 
+    /// Returns the errors recovered from while parsing.
+    pub fn into_errors(self) -> Vec<ParserError> {
+        self.errors
+    }
+
+    /// Sets the flags that `#[cfg(...)]` attributes are evaluated against.
+    pub fn with_defines(mut self, defines: HashMap<String, Option<String>>) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    /// Returns the token `n` places ahead without consuming it. A source that
+    /// runs dry without producing `Eof` is terminated with one.
+    fn peek_nth(&mut self, n: usize) -> Option<(Token, Position)> {
+        while self.lookahead.len() <= n {
+            if let Some((Token::Eof, _)) = self.lookahead.back() {
+                return None;
+            }
+            let next = self.source.next().unwrap_or_else(|| {
+                (Token::Eof, Position { line: self.last_position.line + 1, index: 1 })
+            });
+            self.last_position = next.1.clone();
+            self.lookahead.push_back(next);
+        }
+        self.lookahead.get(n).cloned()
+    }
+
     pub fn parse(&mut self, ctx: &mut SemanticContext) -> Result<Box<Ast>, ParserError> {
         let mut ast = Box::new(Ast::new());
 
         while let Ok((token, pos)) = self.consume() {
+            // `#[cfg(...)]` decides whether the item that follows is compiled at all.
+            let (token, pos) = match token {
+                Token::Hash => {
+                    match self.parse_cfg_attribute() {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            if let Err(e) = self.skip_item() {
+                                self.errors.push(e);
+                            }
+                            continue;
+                        }
+                        Err(e) => {
+                            self.recover(e);
+                            continue;
+                        }
+                    }
+                    match self.consume() {
+                        Ok(item) => item,
+                        Err(_) => break,
+                    }
+                }
+                token => (token, pos),
+            };
+
+            // A leading `pub` marks the item that follows as public.
+            let (is_public, (token, pos)) = match token {
+                Token::Pub => match self.consume() {
+                    Ok(item) => (true, item),
+                    Err(_) => break,
+                },
+                token => (false, (token, pos)),
+            };
+
             match token {
                 Token::Fn => {
-                    match self.parse_fn(ctx) {
+                    match self.parse_fn(ctx, is_public) {
                         Ok(func) => {
                             ast.children.push(Box::new(func));
                         }
                         Err(e) => {
-                            eprintln!("{}", e);
+                            self.recover(e);
                         }
                     }
                     // Add the parsed function to the AST
                 }
+                Token::Struct => match self.parse_struct(is_public) {
+                    Ok(definition) => ast.children.push(Box::new(definition)),
+                    Err(e) => self.recover(e),
+                },
+                Token::Static if !is_public => match self.parse_static(ctx) {
+                    Ok(declaration) => ast.children.push(Box::new(declaration)),
+                    Err(e) => self.recover(e),
+                },
                 token => {
-                    // Skip unexpected tokens or handle other cases
-                    println!(
-                        "Token: {:?} on line {} at index {}",
-                        token, pos.line, pos.index
-                    );
-                    todo!("[token] parse()")
+                    // Only items may appear at the top level.
+                    self.recover(ParserError::UnexpectedToken {
+                        token,
+                        file: self.file.clone(),
+                        position: pos,
+                    });
                 }
             }
         }
@@ -151,9 +299,93 @@ impl Parser {
         Ok(ast)
     }
 
-    pub fn parse_fn<'a>(
+    /// Records an error in a top-level item and skips ahead to the start of the
+    /// next one, so a single mistake doesn't end parsing or cascade.
+    fn recover(&mut self, error: ParserError) {
+        self.errors.push(error);
+        while let Some((token, _)) = self.peek() {
+            if matches!(
+                token,
+                Token::Eof | Token::Fn | Token::Struct | Token::Static | Token::Pub | Token::Hash
+            ) {
+                return;
+            }
+            self.lookahead.pop_front();
+        }
+    }
+
+    /// Parses `[cfg(name)]` or `[cfg(name = "value")]` after a '#', returning
+    /// whether the condition holds for the flags given with `-D`.
+    fn parse_cfg_attribute(&mut self) -> Result<bool, ParserError> {
+        // Pattern: LBracket, Identifier(cfg), LPar, Identifier, [Equal, StringLiteral], RPar, RBracket.
+        self.expect(Token::LBracket, "'[' after '#'")?;
+
+        let (token, pos) = self.consume()?;
+        if token != Token::Identifier("cfg".to_string()) {
+            return Err(ParserError::SyntaxError {
+                message: format!("Unknown attribute {:?}, only `cfg` is supported", token),
+                file: self.file.clone(),
+                position: pos,
+            });
+        }
+        self.expect(Token::LPar, "'(' after 'cfg'")?;
+
+        let (token, pos) = self.consume()?;
+        let Token::Identifier(name) = token else {
+            return Err(ParserError::MissingToken {
+                expected: "flag name in 'cfg'".to_string(),
+                file: self.file.clone(),
+                position: pos,
+            });
+        };
+
+        let expected_value = if let Some((Token::Equal, _)) = self.peek() {
+            self.consume()?; // Consume '='
+            match self.consume()? {
+                (Token::StringLiteral(value), _) => Some(value),
+                (_, pos) => {
+                    return Err(ParserError::MissingToken {
+                        expected: "string value in 'cfg'".to_string(),
+                        file: self.file.clone(),
+                        position: pos,
+                    });
+                }
+            }
+        } else {
+            None
+        };
+
+        self.expect(Token::RPar, "')' closing 'cfg'")?;
+        self.expect(Token::RBracket, "']' closing the attribute")?;
+
+        Ok(match (self.defines.get(&name), expected_value) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(value), Some(expected)) => value.as_deref() == Some(expected.as_str()),
+        })
+    }
+
+    /// Skips an item excluded by `cfg` without parsing it: everything up to and
+    /// including the '}' that closes its first '{', or the ';' ending an item
+    /// without a body, such as a `static`.
+    fn skip_item(&mut self) -> Result<(), ParserError> {
+        let mut depth = 0;
+        loop {
+            let (token, _) = self.consume()?;
+            match token {
+                Token::LCurl => depth += 1,
+                Token::RCurl if depth == 1 => return Ok(()),
+                Token::RCurl => depth -= 1,
+                Token::Semicolon if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn parse_fn(
         &mut self,
         ctx: &mut SemanticContext,
+        is_public: bool,
     ) -> Result<FunctionDefinition, ParserError> {
         // Expect a function name
         let func_name = match self.consume() {
@@ -168,39 +400,80 @@ impl Parser {
             Err(e) => return Err(e),
         };
 
-        let parameters = match self.parse_fn_parameters(ctx) {
-            Ok(params) => params,
-            Err(e) => {
-                return Err(e);
-            }
-        };
-
-        // Parse return type
-        let return_type = match self.parse_fn_return_type() {
-            Ok(ret) => ret,
-            Err(e) => {
-                // Change later
-                return Err(e);
-            }
-        };
-
-        // Parse the function body
-        let body = match self.parse_fn_body(ctx) {
-            Ok(bod) => bod,
-            Err(e) => {
-                // Change later
-                return Err(e);
-            }
-        };
+        // Parameters are visible only inside the function.
+        let (parameters, return_type, where_clause, body) = self.scoped(ctx, |parser, ctx| {
+            let parameters = parser.parse_fn_parameters(ctx)?;
+            let return_type = parser.parse_fn_return_type()?;
+            let where_clause = parser.parse_where_clause()?;
+            let body = parser.parse_body(ctx)?;
+            Ok((parameters, return_type, where_clause, body))
+        })?;
 
         Ok(FunctionDefinition {
             id: func_name,
+            is_public,
             parameters,
             return_type,
+            where_clause,
             body: Box::new(body),
         })
     }
 
+    fn parse_struct(&mut self, is_public: bool) -> Result<StructDefinition, ParserError> {
+        // Pattern: Identifier, LCurl, [Identifier, Colon, Type, (Comma)]*, RCurl.
+        let (name_token, name_pos) = self.consume()?;
+        let Token::Identifier(name) = name_token else {
+            return Err(ParserError::MissingToken {
+                expected: "struct name".to_string(),
+                file: self.file.clone(),
+                position: name_pos,
+            });
+        };
+
+        self.expect(Token::LCurl, "'{' after struct name")?;
+
+        let mut fields = Vec::new();
+        loop {
+            let (token, pos) = self.consume()?;
+            let field = match token {
+                Token::RCurl => break,
+                Token::Identifier(field) => field,
+                _ => {
+                    return Err(ParserError::MissingToken {
+                        expected: "field name or '}'".to_string(),
+                        file: self.file.clone(),
+                        position: pos,
+                    });
+                }
+            };
+
+            self.expect(Token::Colon, "':' after field name")?;
+            let field_type = self.parse_type("field type")?;
+            fields.push((field, field_type));
+
+            // Fields are separated by commas; the last one may omit it.
+            match self.peek() {
+                Some((Token::Comma, _)) => {
+                    self.consume()?;
+                }
+                Some((Token::RCurl, _)) => {}
+                _ => {
+                    let (_, pos) = self.consume()?;
+                    return Err(ParserError::MissingToken {
+                        expected: "',' or '}' after struct field".to_string(),
+                        file: self.file.clone(),
+                        position: pos,
+                    });
+                }
+            }
+        }
+
+        Ok(StructDefinition {
+            definition: StructType { name, fields },
+            is_public,
+        })
+    }
+
     fn parse_fn_parameters(
         &mut self,
         ctx: &mut SemanticContext,
@@ -208,14 +481,7 @@ impl Parser {
         let mut parameters = Vec::new();
 
         // Expect an opening parenthesis.
-        let (lpar, pos) = self.consume()?;
-        if lpar != Token::LPar {
-            return Err(ParserError::MissingToken {
-                expected: "opening '('".to_string(),
-                file: self.file.clone(),
-                position: pos,
-            });
-        }
+        self.expect(Token::LPar, "opening '('")?;
 
         // If immediately a right parenthesis, then there are no parameters.
         if let Some((Token::RPar, _)) = self.peek() {
@@ -238,46 +504,10 @@ impl Parser {
             };
 
             // Expect a colon after the parameter name.
-            let (colon, colon_pos) = self.consume()?;
-            if colon != Token::Colon {
-                return Err(ParserError::SyntaxError {
-                    message: "Expected ':' after parameter name.".to_string(),
-                    file: self.file.clone(),
-                    position: colon_pos,
-                });
-            }
+            let colon_pos = self.expect(Token::Colon, "':' after parameter name")?;
 
             // Parse the parameter type.
-            let (type_token, type_pos) = self.consume()?;
-            let param_type = match type_token {
-                Token::I32 => Type::basic("i32"),
-                Token::I64 => Type::basic("i64"),
-                Token::U32 => Type::basic("u32"),
-                Token::U64 => Type::basic("u64"),
-                // For types that are not built-in primitives,
-                // we assume the token is an identifier (e.g. a struct name or type alias)
-                Token::Identifier(id) => {
-                    /*
-                    match ctx.lookup(id) {
-                        Some(t) => {
-                            unreachable!()
-                        }
-                        None => { unreachable!() }
-                    }
-                    */
-
-                    // Need to lookup the type to see if it exists
-
-                    Type::Custom(id)
-                }
-                _ => {
-                    return Err(ParserError::MissingToken {
-                        expected: "parameter type".to_string(),
-                        file: self.file.clone(),
-                        position: type_pos,
-                    });
-                }
-            };
+            let param_type = self.parse_type("parameter type")?;
 
             ctx.add_symbol(&param_name, Symbol::Variable(param_type.clone()));
 
@@ -285,6 +515,8 @@ impl Parser {
             parameters.push(FunctionParameter {
                 id: param_name,
                 r#type: param_type,
+                position: pos.clone(),
+                slot: RefCell::new(None),
             });
 
             // Now, check if there is a comma or the close parenthesis.
@@ -292,7 +524,12 @@ impl Parser {
                 match next_token {
                     Token::Comma => {
                         self.consume()?; // Consume the comma.
-                                         // Continue to parse the next parameter.
+                        // A trailing comma may directly precede the closing parenthesis.
+                        if let Some((Token::RPar, _)) = self.peek() {
+                            self.consume()?;
+                            break;
+                        }
+                        // Continue to parse the next parameter.
                         continue;
                     }
                     Token::RPar => {
@@ -304,11 +541,7 @@ impl Parser {
                             token: next_token,
                             file: self.file.clone(),
                             // Using a cloned current position (you might want to create a helper for this):
-                            position: self
-                                .tokens
-                                .get(self.position)
-                                .map(|(_, pos)| pos.clone())
-                                .unwrap_or(pos),
+                            position: self.peek().map(|(_, pos)| pos).unwrap_or(pos),
                         });
                     }
                 }
@@ -316,7 +549,7 @@ impl Parser {
                 return Err(ParserError::MissingToken {
                     expected: "',' or ')'".to_string(),
                     file: self.file.clone(),
-                    position: type_pos,
+                    position: colon_pos,
                 });
             }
         }
@@ -325,55 +558,94 @@ impl Parser {
     }
 
     fn parse_fn_return_type(&mut self) -> Result<FunctionReturnType, ParserError> {
-        let mut return_type = FunctionReturnType(Type::basic("void"));
+        // Without an arrow the return type is left to be inferred from the body,
+        // and the following '{' is left for `parse_body`.
+        if let Some((Token::Arrow, _)) = self.peek() {
+            self.consume()?;
+            return Ok(FunctionReturnType(Some(self.parse_type("return type")?)));
+        }
 
-        match self.consume() {
-            Ok((Token::Arrow, _)) => match self.consume() {
-                Ok((Token::I32, _)) => {
-                    return_type.0 = Type::basic("i32");
-                }
-                x => {
-                    dbg!(x);
-                    todo!("[x] parse_fn_return_type()");
-                }
-            },
-            Ok((Token::Semicolon, _)) => {
-                return Ok(return_type);
-            }
-            Ok((Token::LCurl, _)) => {
-                return Ok(return_type);
-            }
-            Ok((token, _)) => {
-                dbg!(token);
-                todo!("[Some(x)] parse_fn_return_type()")
-            }
-            Err(e) => {
-                println!("{:?}", e);
-                /* return Err(ParserError::MissingToken {
-                    expected: String::from("'->' or '{' or ';'"),
+        Ok(FunctionReturnType(None))
+    }
+
+    /// Runs `parse` in a new scope, so the names it declares are forgotten afterwards, even on error.
+    fn scoped<T>(
+        &mut self,
+        ctx: &mut SemanticContext,
+        parse: impl FnOnce(&mut Self, &mut SemanticContext) -> Result<T, ParserError>,
+    ) -> Result<T, ParserError> {
+        ctx.enter_scope();
+        let result = parse(self, ctx);
+        ctx.exit_scope();
+        result
+    }
+
+    /// Parses an optional `where T: Bound + Bound, U: Bound` clause before a function body.
+    fn parse_where_clause(&mut self) -> Result<Option<WhereClause>, ParserError> {
+        let Some((Token::Where, position)) = self.peek() else {
+            return Ok(None);
+        };
+        self.consume()?; // Consume 'where'
+
+        let mut constraints = Vec::new();
+        loop {
+            let (token, pos) = self.consume()?;
+            let Token::Identifier(parameter) = token else {
+                return Err(ParserError::MissingToken {
+                    expected: "type parameter in where clause".to_string(),
                     file: self.file.clone(),
                     position: pos,
-                }); */
+                });
+            };
+            self.expect(Token::Colon, "':' after type parameter in where clause")?;
 
-                return Err(e);
+            let mut bounds = Vec::new();
+            loop {
+                let (token, pos) = self.consume()?;
+                let Token::Identifier(bound) = token else {
+                    return Err(ParserError::MissingToken {
+                        expected: "trait bound in where clause".to_string(),
+                        file: self.file.clone(),
+                        position: pos,
+                    });
+                };
+                bounds.push(bound);
+                if let Some((Token::Plus, _)) = self.peek() {
+                    self.consume()?;
+                } else {
+                    break;
+                }
+            }
+            constraints.push((parameter, bounds));
+
+            // Constraints are comma separated and the clause ends at the body's '{'.
+            match self.peek() {
+                Some((Token::Comma, _)) => {
+                    self.consume()?;
+                    if let Some((Token::LCurl, _)) = self.peek() {
+                        break;
+                    }
+                }
+                _ => break,
             }
         }
 
-        return Ok(return_type);
+        Ok(Some(WhereClause {
+            constraints,
+            position,
+        }))
+    }
+
+    fn parse_body(&mut self, ctx: &mut SemanticContext) -> Result<Body, ParserError> {
+        // Declarations are only visible until the closing '}'.
+        self.scoped(ctx, Self::parse_body_statements)
     }
 
-    fn parse_fn_body(&mut self, ctx: &mut SemanticContext) -> Result<FunctionBody, ParserError> {
+    fn parse_body_statements(&mut self, ctx: &mut SemanticContext) -> Result<Body, ParserError> {
         // Expect an opening curly brace and consume it.
-        let (lcurly, pos) = self.consume()?;
-        if lcurly != Token::LCurl {
-            return Err(ParserError::MissingToken {
-                expected: "opening '{'".to_string(),
-                file: self.file.clone(),
-                position: pos,
-            });
-        }
+        self.expect(Token::LCurl, "opening '{'")?;
 
-        let mut body = FunctionBody {
+        let mut body = Body {
             children: Vec::new(),
         };
 
@@ -388,31 +660,78 @@ impl Parser {
         }
 
         // Now, expect and consume the closing curly.
-        let (rcurly, pos) = self.consume()?;
-        if rcurly != Token::RCurl {
-            return Err(ParserError::MissingToken {
-                expected: "closing '}'".to_string(),
-                file: self.file.clone(),
-                position: pos,
-            });
-        }
+        self.expect(Token::RCurl, "closing '}'")?;
         Ok(body)
     }
 
+    /// Parses the rest of a block expression after its opening `{`.
+    ///
+    /// An expression followed directly by `}` becomes the block's value.
+    fn parse_block_expr(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
+        let mut body = Body {
+            children: Vec::new(),
+        };
+        let mut value = None;
+
+        while let Some((token, _)) = self.peek() {
+            if token == Token::RCurl {
+                break;
+            }
+
+            // Declarations, assignments and control flow are plain statements.
+            let second = self.peek_nth(1).map(|(t, _)| t);
+            let is_statement = matches!(
+                (&token, second),
+                (
+                    Token::Ret
+                        | Token::If
+                        | Token::While
+                        | Token::For
+                        | Token::Match
+                        | Token::Break
+                        | Token::Continue
+                        | Token::Const,
+                    _,
+                )
+                    | (Token::Identifier(_), Some(Token::Colon | Token::Walrus | Token::Equal))
+            );
+            if is_statement {
+                body.children.push(self.parse_statement(ctx)?);
+                continue;
+            }
+
+            let expr = self.parse_ternary(ctx)?;
+            match self.peek() {
+                Some((Token::Semicolon, _)) => {
+                    self.consume()?;
+                    body.children.push(Box::new(ExpressionStatement { expression: expr }));
+                }
+                Some((Token::RCurl, _)) => {
+                    value = Some(expr);
+                    break;
+                }
+                _ => {
+                    let (_, pos) = self.consume()?;
+                    return Err(ParserError::MissingToken {
+                        expected: "';' or '}' after expression in block".to_string(),
+                        file: self.file.clone(),
+                        position: pos,
+                    });
+                }
+            }
+        }
+
+        self.expect(Token::RCurl, "closing '}'")?;
+        Ok(Expr::Block(Box::new(BlockExpr::new(body, value))))
+    }
+
     fn parse_fn_call(
         &mut self,
         ctx: &mut SemanticContext,
         function_id: String,
     ) -> Result<Expr, ParserError> {
         // Consume the left parenthesis. We already know the next token is LPar.
-        let (lpar, pos) = self.consume()?;
-        if lpar != Token::LPar {
-            return Err(ParserError::SyntaxError {
-                message: "Expected '(' after function name".to_string(),
-                file: self.file.clone(),
-                position: pos,
-            });
-        }
+        let pos = self.expect(Token::LPar, "'(' after function name")?;
 
         let mut arguments = Vec::new();
 
@@ -420,15 +739,17 @@ impl Parser {
         if let Some((Token::RPar, _)) = self.peek() {
             self.consume()?; // Consume RPar
             return Ok(Expr::FunctionCall {
+                id: self.node_id(),
                 function: function_id,
                 arguments,
+                folded: Cell::new(None),
             });
         }
 
         // Otherwise, loop to parse arguments.
         loop {
             // Parse an expression argument.
-            let arg = self.parse_expression(ctx)?;
+            let arg = self.parse_ternary(ctx)?;
             arguments.push(arg);
 
             // Peek at the next token to decide what to do.
@@ -436,6 +757,11 @@ impl Parser {
                 match next_token {
                     Token::Comma => {
                         self.consume()?; // Consume the comma and continue
+                        // A trailing comma may directly precede the closing parenthesis.
+                        if let Some((Token::RPar, _)) = self.peek() {
+                            self.consume()?;
+                            break;
+                        }
                     }
                     Token::RPar => {
                         self.consume()?; // Consume the closing parenthesis and exit the loop.
@@ -459,13 +785,91 @@ impl Parser {
         }
 
         Ok(Expr::FunctionCall {
+            id: self.node_id(),
             function: function_id,
             arguments,
+            folded: Cell::new(None),
         })
     }
 
     // --- Expression Parsing Functions ---
 
+    /// Parses a conditional expression `cond ? then : els`, the loosest binding level.
+    fn parse_ternary(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
+        let cond = self.parse_logical_or(ctx)?;
+        if let Some((Token::Question, _)) = self.peek() {
+            self.consume()?; // Consume '?'
+            let then = self.parse_ternary(ctx)?;
+            self.expect(Token::Colon, "':' in ternary expression")?;
+            let els = self.parse_ternary(ctx)?;
+            return Ok(Expr::Ternary {
+                id: self.node_id(),
+                cond: Box::new(cond),
+                then: Box::new(then),
+                els: Box::new(els),
+            });
+        }
+        Ok(cond)
+    }
+
+    /// Parses `||` chains, which bind looser than `&&`.
+    fn parse_logical_or(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
+        let mut expr = self.parse_logical_and(ctx)?;
+        while let Some((Token::Or, _)) = self.peek() {
+            self.consume()?; // Consume '||'
+            let right = self.parse_logical_and(ctx)?;
+            expr = Expr::Binary(Box::new(BinaryExpr {
+                id: self.node_id(),
+                op: Operator::Or,
+                left: expr,
+                right,
+            }));
+        }
+        Ok(expr)
+    }
+
+    /// Parses `&&` chains, which bind looser than comparisons.
+    fn parse_logical_and(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
+        let mut expr = self.parse_comparison(ctx)?;
+        while let Some((Token::And, _)) = self.peek() {
+            self.consume()?; // Consume '&&'
+            let right = self.parse_comparison(ctx)?;
+            expr = Expr::Binary(Box::new(BinaryExpr {
+                id: self.node_id(),
+                op: Operator::And,
+                left: expr,
+                right,
+            }));
+        }
+        Ok(expr)
+    }
+
+    /// Parses comparisons and equality, which bind looser than arithmetic.
+    fn parse_comparison(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
+        let mut expr = self.parse_expression(ctx)?;
+        while let Some((token, _)) = self.peek() {
+            let op = match token {
+                Token::EqEq => Operator::Equals,
+                Token::NotEq => Operator::NotEquals,
+                Token::Lt => Operator::Less,
+                Token::Gt => Operator::Greater,
+                Token::Le => Operator::LessEquals,
+                Token::Ge => Operator::GreaterEquals,
+                Token::Compare => Operator::Compare,
+                _ => break,
+            };
+            self.consume()?; // Consume the operator.
+            let right = self.parse_expression(ctx)?;
+            expr = Expr::Binary(Box::new(BinaryExpr {
+                id: self.node_id(),
+                op,
+                left: expr,
+                right,
+            }));
+        }
+        Ok(expr)
+    }
+
     /// Parses an expression, handling addition and subtraction.
     fn parse_expression(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
         let mut expr = self.parse_term(ctx)?;
@@ -482,6 +886,7 @@ impl Parser {
                         _ => unreachable!(),
                     };
                     expr = Expr::Binary(Box::new(BinaryExpr {
+                        id: self.node_id(),
                         op,
                         left: expr,
                         right,
@@ -495,12 +900,12 @@ impl Parser {
 
     /// Parses a term, handling multiplication, division, and modulus.
     fn parse_term(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
-        let mut expr = self.parse_factor(ctx)?;
+        let mut expr = self.parse_cast(ctx)?;
         while let Some((token, _)) = self.peek() {
             match token {
                 Token::Asterisk | Token::Fslash | Token::Percent => {
                     let (op_token, _) = self.consume()?; // consume the operator
-                    let right = self.parse_factor(ctx)?;
+                    let right = self.parse_cast(ctx)?;
                     let op = match op_token {
                         Token::Asterisk => Operator::Asterisk,
                         Token::Fslash => Operator::Fslash,
@@ -508,6 +913,7 @@ impl Parser {
                         _ => unreachable!(),
                     };
                     expr = Expr::Binary(Box::new(BinaryExpr {
+                        id: self.node_id(),
                         op,
                         left: expr,
                         right,
@@ -519,13 +925,32 @@ impl Parser {
         Ok(expr)
     }
 
+    /// Parses `factor as Type`, which binds tighter than any binary operator.
+    fn parse_cast(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
+        let mut expr = self.parse_factor(ctx)?;
+        while let Some((Token::As, _)) = self.peek() {
+            self.consume()?; // Consume 'as'
+            let target = self.parse_type("type after 'as'")?;
+            expr = Expr::Cast(Box::new(Cast::new(self.node_id(), expr, target)));
+        }
+        Ok(expr)
+    }
+
     /// Parses a factor: a number, an identifier, or a parenthesized expression.
     fn parse_factor(&mut self, ctx: &mut SemanticContext) -> Result<Expr, ParserError> {
         let (token, pos) = self.consume()?;
         match token {
-            Token::NumberLiteral(num) => Ok(Expr::Number(num.parse::<i64>().unwrap())),
+            Token::NumberLiteral(num) => match NumberBase::parse(&num) {
+                Some((value, base)) => Ok(Expr::Number(value, base)),
+                None => Err(ParserError::SyntaxError {
+                    message: format!("Invalid integer literal '{}'", num),
+                    file: self.file.clone(),
+                    position: pos,
+                }),
+            },
             Token::CharacterLiteral(ch) => Ok(Expr::Character(ch)),
             Token::StringLiteral(str) => Ok(Expr::String(str)),
+            Token::BooleanLiteral(value) => Ok(Expr::Boolean(value)),
             Token::Identifier(id) => {
                 // If a left paren follows, this is a function call.
                 if let Some((next_token, _)) = self.peek() {
@@ -534,20 +959,57 @@ impl Parser {
                     }
                 }
                 // Otherwise, it's a variable reference.
-
-                dbg!(&ctx.symbol_table);
-
                 match ctx.lookup(&id) {
                     Some(s) => {
-                        Ok(Expr::VariableCall{ id, resolved: Some(s.clone()) } )
+                        Ok(Expr::VariableCall{ id, resolved: Some(s.clone()), slot: RefCell::new(None) } )
                     }
                     None => {
-                        Ok(Expr::Identifier(id))
+                        Ok(Expr::Identifier { id, slot: RefCell::new(None) })
                     }
                 }
             }
+            Token::LCurl => self.scoped(ctx, Self::parse_block_expr),
+            Token::At => {
+                // Builtins bypass the symbol table: `@name(arguments)`.
+                let (name_token, name_pos) = self.consume()?;
+                let Token::Identifier(name) = name_token else {
+                    return Err(ParserError::MissingToken {
+                        expected: "builtin name after '@'".to_string(),
+                        file: self.file.clone(),
+                        position: name_pos,
+                    });
+                };
+                match self.parse_fn_call(ctx, name)? {
+                    Expr::FunctionCall { function, arguments, .. } => Ok(Expr::Builtin(Box::new(BuiltinCall {
+                        name: function,
+                        arguments,
+                        position: pos,
+                    }))),
+                    _ => unreachable!(),
+                }
+            }
             Token::LPar => {
-                let expr = self.parse_expression(ctx)?;
+                // `()` is the unit value, `(a)` groups and `(a, b, ...)` builds a tuple.
+                if let Some((Token::RPar, _)) = self.peek() {
+                    self.consume()?;
+                    return Ok(Expr::Tuple(Vec::new()));
+                }
+
+                let expr = self.parse_ternary(ctx)?;
+                if let Some((Token::Comma, _)) = self.peek() {
+                    let mut elements = vec![expr];
+                    while let Some((Token::Comma, _)) = self.peek() {
+                        self.consume()?;
+                        // A trailing comma is allowed, so `(a,)` is a one-element tuple.
+                        if let Some((Token::RPar, _)) = self.peek() {
+                            break;
+                        }
+                        elements.push(self.parse_ternary(ctx)?);
+                    }
+                    self.expect(Token::RPar, "')' closing the tuple")?;
+                    return Ok(Expr::Tuple(elements));
+                }
+
                 match self.consume()? {
                     (Token::RPar, _) => Ok(expr),
                     (unexpected, pos) => Err(ParserError::UnexpectedToken {
@@ -569,21 +1031,51 @@ impl Parser {
         // First, if the statement starts with 'ret', handle it.
         if let Some((Token::Ret, _)) = self.peek() {
             let (_, _) = self.consume()?; // Consume 'ret'
-            let expr = self.parse_expression(ctx)?;
-            let (next_token, next_pos) = self.consume()?;
-            if next_token != Token::Semicolon {
-                return Err(ParserError::SyntaxError {
-                    message: "Expected ';' after return expression.".to_string(),
-                    file: self.file.clone(),
-                    position: next_pos,
-                });
-            }
-            return Ok(Box::new(Return { value: expr }));
+            // A bare `ret;` returns from a `void` function early.
+            let value = match self.peek() {
+                Some((Token::Semicolon, _)) => None,
+                _ => Some(self.parse_ternary(ctx)?),
+            };
+            self.expect(Token::Semicolon, "';' after return expression")?;
+            return Ok(Box::new(Return { value }));
+        }
+
+        if let Some((Token::If, _)) = self.peek() {
+            return Ok(Box::new(self.parse_if(ctx)?));
+        }
+
+        if let Some((Token::Const, _)) = self.peek() {
+            return self.parse_const_decl(ctx);
+        }
+
+        if let Some((Token::While, _)) = self.peek() {
+            return self.parse_while(ctx);
+        }
+
+        if let Some((Token::For, _)) = self.peek() {
+            // The loop variable is scoped to the loop.
+            return self.scoped(ctx, Self::parse_for);
+        }
+
+        if let Some((Token::Match, _)) = self.peek() {
+            return self.parse_match(ctx);
+        }
+
+        if let Some((Token::Break, _)) = self.peek() {
+            let (_, position) = self.consume()?; // Consume 'break'
+            self.expect(Token::Semicolon, "';' after break")?;
+            return Ok(Box::new(Break { position }));
+        }
+
+        if let Some((Token::Continue, _)) = self.peek() {
+            let (_, position) = self.consume()?; // Consume 'continue'
+            self.expect(Token::Semicolon, "';' after continue")?;
+            return Ok(Box::new(Continue { position }));
         }
 
         // If the statement begins with an identifier, check the second token.
         if let Some((Token::Identifier(_), pos)) = self.peek() {
-            let second = self.tokens.get(self.position + 1);
+            let second = self.peek_nth(1);
             if let Some((second_token, _)) = second {
                 match second_token {
                     Token::Colon => {
@@ -600,7 +1092,7 @@ impl Parser {
                     }
                     _ => {
                         // Fall back to parsing an expression statement.
-                        let expr = self.parse_expression(ctx)?;
+                        let expr = self.parse_ternary(ctx)?;
                         if let Some((Token::Semicolon, _)) = self.peek() {
                             self.consume()?; // consume semicolon.
                         }
@@ -611,8 +1103,8 @@ impl Parser {
         }
 
         // If starting token is a number or left parenthesis, treat it as an expression.
-        if let Some((Token::NumberLiteral(_) | Token::LPar, _)) = self.peek() {
-            let expr = self.parse_expression(ctx)?;
+        if let Some((Token::NumberLiteral(_) | Token::BooleanLiteral(_) | Token::LPar | Token::At, _)) = self.peek() {
+            let expr = self.parse_ternary(ctx)?;
             if let Some((Token::Semicolon, _)) = self.peek() {
                 self.consume()?;
             }
@@ -628,45 +1120,195 @@ impl Parser {
         })
     }
 
-    fn parse_assignment(
-        &mut self,
-        ctx: &mut SemanticContext,
-    ) -> Result<Box<dyn Node>, ParserError> {
-        // Pattern: Identifier, Equal, Expression, Semicolon.
+    fn parse_if(&mut self, ctx: &mut SemanticContext) -> Result<IfStatement, ParserError> {
+        // Pattern: If, Expression, Body, [Else, (Body | IfStatement)].
+        let (_, position) = self.consume()?; // Consume 'if'
+        let condition = self.parse_ternary(ctx)?;
+        let body = self.parse_body(ctx)?;
 
-        // Consume the LHS identifier.
-        let (id_token, _) = self.consume()?;
-        let lhs = if let Token::Identifier(name) = id_token {
-            name
+        let else_statement = if let Some((Token::Else, _)) = self.peek() {
+            self.consume()?; // Consume 'else'
+
+            // An `else if` chain is stored as an else-body holding the nested if.
+            let body = if let Some((Token::If, _)) = self.peek() {
+                Body {
+                    children: vec![Box::new(self.parse_if(ctx)?)],
+                }
+            } else {
+                self.parse_body(ctx)?
+            };
+            Some(ElseStatement { body })
         } else {
-            unreachable!("Expected an identifier as the left-hand side of an assignment.")
+            None
         };
 
-        // Consume the '=' token.
-        let (equal, pos) = self.consume()?;
-        if equal != Token::Equal {
+        Ok(IfStatement {
+            condition,
+            body,
+            else_statement,
+            position,
+        })
+    }
+
+    fn parse_while(&mut self, ctx: &mut SemanticContext) -> Result<Box<dyn Node>, ParserError> {
+        // Pattern: While, Expression, Body.
+        let (_, position) = self.consume()?; // Consume 'while'
+        let condition = self.parse_ternary(ctx)?;
+        let body = self.parse_body(ctx)?;
+
+        Ok(Box::new(WhileLoop {
+            condition,
+            body,
+            position,
+        }))
+    }
+
+    fn parse_for(&mut self, ctx: &mut SemanticContext) -> Result<Box<dyn Node>, ParserError> {
+        // Pattern: For, Declaration, Expression, Semicolon, Assignment, Body.
+        let (_, position) = self.consume()?; // Consume 'for'
+
+        // The declaration helpers consume the ';' that ends the initializer.
+        let init = match (self.peek(), self.peek_nth(1)) {
+            (Some((Token::Identifier(_), _)), Some((Token::Colon, _))) => self.parse_explicit_decl(ctx)?,
+            (Some((Token::Identifier(_), _)), Some((Token::Walrus, _))) => self.parse_walrus_decl(ctx)?,
+            (_, _) => {
+                return Err(ParserError::SyntaxError {
+                    message: "Expected a variable declaration to start the for loop".to_string(),
+                    file: self.file.clone(),
+                    position,
+                });
+            }
+        };
+
+        let condition = self.parse_ternary(ctx)?;
+        let (token, pos) = self.consume()?;
+        if token != Token::Semicolon {
             return Err(ParserError::SyntaxError {
-                message: "Expected '=' in assignment statement.".to_string(),
+                message: "Expected ';' after the for loop condition".to_string(),
                 file: self.file.clone(),
                 position: pos,
             });
         }
 
-        // Parse the expression for the right-hand side.
-        let expr = self.parse_expression(ctx)?;
+        let step = self.parse_assignment_expr(ctx)?;
+        let body = self.parse_body(ctx)?;
 
-        // Expect a terminating semicolon.
-        let (semi, pos) = self.consume()?;
-        if semi != Token::Semicolon {
-            return Err(ParserError::SyntaxError {
-                message: "Expected ';' after assignment.".to_string(),
+        Ok(Box::new(ForLoop {
+            init,
+            condition,
+            step,
+            body,
+            position,
+        }))
+    }
+
+    fn parse_match(&mut self, ctx: &mut SemanticContext) -> Result<Box<dyn Node>, ParserError> {
+        // Pattern: Match, Expression, LCurl, [Pattern, FatArrow, Body, (Comma)]*, RCurl.
+        let (_, position) = self.consume()?; // Consume 'match'
+        let scrutinee = self.parse_ternary(ctx)?;
+        self.expect(Token::LCurl, "'{' after match scrutinee")?;
+
+        let mut arms = Vec::new();
+        while !matches!(self.peek(), Some((Token::RCurl, _)) | None) {
+            let pattern = self.parse_pattern()?;
+            self.expect(Token::FatArrow, "'=>' after match pattern")?;
+            let body = self.parse_body(ctx)?;
+            arms.push(MatchArm { pattern, body });
+
+            // Arms may be separated by commas.
+            if let Some((Token::Comma, _)) = self.peek() {
+                self.consume()?;
+            }
+        }
+        self.expect(Token::RCurl, "'}' closing the match")?;
+
+        Ok(Box::new(MatchStatement {
+            scrutinee,
+            arms,
+            position,
+        }))
+    }
+
+    /// Parses a match pattern: `_`, an integer literal, or an inclusive range `start..=end`.
+    fn parse_pattern(&mut self) -> Result<Pattern, ParserError> {
+        if let Some((Token::Identifier(name), _)) = self.peek() {
+            if name == "_" {
+                self.consume()?;
+                return Ok(Pattern::Wildcard);
+            }
+        }
+
+        let start = self.parse_pattern_integer()?;
+        if let Some((Token::DotDotEqual, _)) = self.peek() {
+            self.consume()?; // Consume '..='
+            let end = self.parse_pattern_integer()?;
+            return Ok(Pattern::Range(start, end));
+        }
+        Ok(Pattern::Literal(start))
+    }
+
+    /// Parses an integer literal in a pattern, with an optional leading '-'.
+    fn parse_pattern_integer(&mut self) -> Result<i64, ParserError> {
+        let negative = matches!(self.peek(), Some((Token::Minus, _)));
+        if negative {
+            self.consume()?;
+        }
+
+        let (token, pos) = self.consume()?;
+        let Token::NumberLiteral(literal) = token else {
+            return Err(ParserError::MissingToken {
+                expected: "integer, range or '_' pattern".to_string(),
                 file: self.file.clone(),
                 position: pos,
             });
+        };
+        match NumberBase::parse(&literal) {
+            Some((value, _)) => Ok(if negative { -value } else { value }),
+            None => Err(ParserError::SyntaxError {
+                message: format!("Invalid integer pattern '{}'", literal),
+                file: self.file.clone(),
+                position: pos,
+            }),
         }
+    }
+
+    fn parse_assignment(
+        &mut self,
+        ctx: &mut SemanticContext,
+    ) -> Result<Box<dyn Node>, ParserError> {
+        // Pattern: Assignment, Semicolon.
+        let assignment = self.parse_assignment_expr(ctx)?;
+
+        // Expect a terminating semicolon.
+        self.expect(Token::Semicolon, "';' after assignment")?;
+
+        Ok(Box::new(assignment))
+    }
+
+    /// Parses `id = value` without a terminating semicolon.
+    fn parse_assignment_expr(&mut self, ctx: &mut SemanticContext) -> Result<Assignment, ParserError> {
+        // Pattern: Identifier, Equal, Expression.
+
+        // Consume the LHS identifier.
+        let (id_token, position) = self.consume()?;
+        let lhs = if let Token::Identifier(name) = id_token {
+            name
+        } else {
+            return Err(ParserError::MissingToken {
+                expected: "variable name in assignment".to_string(),
+                file: self.file.clone(),
+                position,
+            });
+        };
+
+        // Consume the '=' token.
+        self.expect(Token::Equal, "'=' in assignment statement")?;
+
+        // Parse the expression for the right-hand side.
+        let expr = self.parse_ternary(ctx)?;
 
         // Build and return an Assignment node.
-        Ok(Box::new(Assignment { lhs, value: expr }))
+        Ok(Assignment::new(lhs, expr, position))
     }
 
     fn parse_explicit_decl(
@@ -674,53 +1316,25 @@ impl Parser {
         ctx: &mut SemanticContext,
     ) -> Result<Box<dyn Node>, ParserError> {
         // Consume the identifier.
-        let (id_token, _) = self.consume()?;
+        let (id_token, id_pos) = self.consume()?;
         let id = if let Token::Identifier(name) = id_token {
             name
         } else {
             return Err(ParserError::UnexpectedToken {
                 token: id_token,
                 file: self.file.clone(),
-                position: self
-                    .tokens
-                    .get(self.position)
-                    .map(|(_, pos)| pos.clone())
-                    .unwrap_or_default(),
+                position: self.peek().map(|(_, pos)| pos).unwrap_or_default(),
             });
         };
 
         // Expect a colon.
-        let (colon, colon_pos) = self.consume()?;
-        if colon != Token::Colon {
-            return Err(ParserError::SyntaxError {
-                message: "Expected ':' after identifier in variable declaration.".to_string(),
-                file: self.file.clone(),
-                position: colon_pos,
-            });
-        }
+        self.expect(Token::Colon, "':' after identifier in variable declaration")?;
 
         // Parse the type.
-        let (type_token, type_pos) = self.consume()?;
-        let var_type = match type_token {
-            Token::I32 => Type::basic("i32"),
-            Token::Char => Type::basic("char"),
-            Token::Str => Type::basic("str"),
-            Token::Identifier(type_name) => Type::basic(type_name.as_str()),
-            _ => {
-                return Err(ParserError::MissingToken {
-                    expected: "variable type".to_string(),
-                    file: self.file.clone(),
-                    position: type_pos,
-                });
-            }
-        };
+        let var_type = self.parse_type("variable type")?;
 
-        match ctx.lookup(&id) {
-            Some(s) => {
-                return Err(ParserError::GenericError(String::from(format!("Id: `{}` is already defined as {:?}", id, s))))
-            }
-            None => { ctx.add_symbol(&id, Symbol::Variable(var_type.clone())) }
-        }
+        // Redeclarations are rejected during semantic analysis, which knows the scopes.
+        ctx.add_symbol(&id, Symbol::Variable(var_type.clone()));
 
         // At this point, we've parsed "<id> : <type>"
         // Check if the next token is an assignment operator.
@@ -728,26 +1342,17 @@ impl Parser {
             // Consume the '=' token.
             self.consume()?;
             // Parse initializer expression.
-            let initializer_expr = self.parse_expression(ctx)?;
+            let initializer_expr = self.parse_ternary(ctx)?;
             // Expect a semicolon.
-            let (semi, semi_pos) = self.consume()?;
-            if semi != Token::Semicolon {
-                return Err(ParserError::SyntaxError {
-                    message: "Expected ';' after declaration assignment.".to_string(),
-                    file: self.file.clone(),
-                    position: semi_pos,
-                });
-            }
+            self.expect(Token::Semicolon, "';' after declaration assignment")?;
             // Build the plain declaration (with no initializer)...
             let decl = VariableDeclaration {
                 id: id.clone(),
                 var_type: var_type.clone(),
+                position: id_pos.clone(),
             };
             // ...and an assignment node with lhs being the variable name.
-            let assign = Assignment {
-                lhs: id,
-                value: initializer_expr,
-            };
+            let assign = Assignment::new(id, initializer_expr, id_pos);
             // Combine them into a DeclarationAssignment node.
             Ok(Box::new(DeclarationAssignment {
                 declaration: decl,
@@ -755,83 +1360,430 @@ impl Parser {
             }))
         } else {
             // Otherwise, if there's no '=' token, this is a plain declaration.
-            let (semi, semi_pos) = self.consume()?;
-            if semi != Token::Semicolon {
-                return Err(ParserError::SyntaxError {
-                    message: "Expected ';' after variable declaration.".to_string(),
-                    file: self.file.clone(),
-                    position: semi_pos,
-                });
-            }
+            self.expect(Token::Semicolon, "';' after variable declaration")?;
             Ok(Box::new(VariableDeclaration {
                 id: id,
                 var_type,
+                position: id_pos,
             }))
         }
     }
 
+    fn parse_const_decl(
+        &mut self,
+        ctx: &mut SemanticContext,
+    ) -> Result<Box<dyn Node>, ParserError> {
+        // Pattern: Const, Identifier, Colon, Type, Equal, Expression, Semicolon.
+        self.consume()?; // Consume 'const'
+
+        let (id_token, id_pos) = self.consume()?;
+        let Token::Identifier(id) = id_token else {
+            return Err(ParserError::MissingToken {
+                expected: "constant name".to_string(),
+                file: self.file.clone(),
+                position: id_pos,
+            });
+        };
+
+        self.expect(Token::Colon, "':' after constant name")?;
+        let const_type = self.parse_type("constant type")?;
+
+        self.expect(Token::Equal, "'=' initializing the constant")?;
+        let value = self.parse_ternary(ctx)?;
+
+        self.expect(Token::Semicolon, "';' after constant declaration")?;
+
+        ctx.add_symbol(&id, Symbol::Const(const_type.clone()));
+
+        Ok(Box::new(ConstDeclaration {
+            const_type,
+            initializer: Assignment::new(id, value, id_pos),
+        }))
+    }
+
+    /// Parses a module-level `static` after its keyword.
+    fn parse_static(&mut self, ctx: &mut SemanticContext) -> Result<StaticDeclaration, ParserError> {
+        // Pattern: Identifier, Colon, Type, Equal, Expression, Semicolon.
+        let (id_token, id_pos) = self.consume()?;
+        let Token::Identifier(id) = id_token else {
+            return Err(ParserError::MissingToken {
+                expected: "static name".to_string(),
+                file: self.file.clone(),
+                position: id_pos,
+            });
+        };
+
+        self.expect(Token::Colon, "':' after static name")?;
+        let static_type = self.parse_type("static type")?;
+
+        self.expect(Token::Equal, "'=' initializing the static")?;
+        let value = self.parse_ternary(ctx)?;
+
+        self.expect(Token::Semicolon, "';' after static declaration")?;
+
+        Ok(StaticDeclaration {
+            static_type,
+            initializer: Assignment::new(id, value, id_pos),
+        })
+    }
+
     fn parse_walrus_decl(
         &mut self,
         ctx: &mut SemanticContext,
     ) -> Result<Box<dyn Node>, ParserError> {
         // Pattern: Identifier, Walrus, Expression, Semicolon.
-        let (id_token, _) = self.consume()?; // Identifier
+        let (id_token, position) = self.consume()?; // Identifier
         let id = if let Token::Identifier(name) = id_token {
             name
         } else {
             unreachable!()
         };
 
-        let (walrus, pos) = self.consume()?; // Expect the walrus operator (":=")
-        if walrus != Token::Walrus {
-            return Err(ParserError::SyntaxError {
-                message: "Expected ':=' after identifier for walrus declaration.".to_string(),
-                file: self.file.clone(),
-                position: pos,
-            });
-        }
+        self.expect(Token::Walrus, "':=' after identifier for walrus declaration")?; // Expect the walrus operator (":=")
 
         // Parse the initializer expression.
-        let expr = self.parse_expression(ctx)?;
+        let expr = self.parse_ternary(ctx)?;
 
         // Expect semicolon.
-        let (semi, pos) = self.consume()?;
-        if semi != Token::Semicolon {
-            return Err(ParserError::SyntaxError {
-                message: "Expected ';' after walrus declaration.".to_string(),
-                file: self.file.clone(),
-                position: pos,
-            });
-        }
+        self.expect(Token::Semicolon, "';' after walrus declaration")?;
 
         ctx.add_symbol(&id, Symbol::Variable(Type::Custom(String::from("<inferred>"))));
 
         Ok(Box::new(WalrusDeclaration {
             id: id,
             initializer: expr,
+            position,
+            slot: RefCell::new(None),
         }))
     }
 
-    fn peek(&self) -> Option<(Token, Position)> {
-        self.tokens.get(self.position).cloned()
+    /// Consumes a type: a primitive keyword or an identifier naming a custom type.
+    /// `what` describes the expected type in the error message.
+    fn parse_type(&mut self, what: &str) -> Result<Type, ParserError> {
+        let (token, pos) = self.consume()?;
+        if let Some(primitive) = token_to_type(&token) {
+            return Ok(primitive);
+        }
+        match token {
+            // Need to lookup the type to see if it exists. `void` is not a keyword,
+            // so it arrives here as an identifier.
+            Token::Identifier(id) => Ok(Type::basic(&id)),
+            _ => Err(ParserError::MissingToken {
+                expected: what.to_string(),
+                file: self.file.clone(),
+                position: pos,
+            }),
+        }
+    }
+
+    /// Consumes the next token, returning its position if it is `expected` and a
+    /// `MissingToken` error naming `what` otherwise.
+    fn expect(&mut self, expected: Token, what: &str) -> Result<Position, ParserError> {
+        let (token, pos) = self.consume()?;
+        if token != expected {
+            return Err(ParserError::MissingToken {
+                expected: what.to_string(),
+                file: self.file.clone(),
+                position: pos,
+            });
+        }
+        Ok(pos)
+    }
+
+    /// Hands out the id of a new expression node.
+    fn node_id(&mut self) -> NodeId {
+        self.node_count += 1;
+        NodeId(self.node_count)
+    }
+
+    fn peek(&mut self) -> Option<(Token, Position)> {
+        self.peek_nth(0)
     }
 
     // Helper method to consume the current token and advance the position
     fn consume(&mut self) -> Result<(Token, Position), ParserError> {
-        if let Some((token, pos)) = self.tokens.get(self.position).cloned() {
-            match token {
-                Token::Eof => Err(ParserError::UnexpectedToken {
-                    token: Token::Eof,
-                    file: self.file.clone(),
-                    position: pos.clone(),
-                }),
-                _ => {
-                    self.position += 1;
-                    Ok((token, pos))
-                }
-            }
-        } else {
-            Err(ParserError::GenericError(String::from("Reached end of Vec<(Token, Position)> for unknown reason, it should have stopped at `Token::Eof`")))
+        match self.peek() {
+            Some((Token::Eof, pos)) => Err(ParserError::UnexpectedToken {
+                token: Token::Eof,
+                file: self.file.clone(),
+                position: pos,
+            }),
+            Some(_) => Ok(self.lookahead.pop_front().unwrap()),
+            None => Err(ParserError::GenericError(String::from("Reached the end of the token stream for unknown reason, it should have stopped at `Token::Eof`"))),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{token_to_type, Parser};
+    use crate::front::nodes::function::FunctionDefinition;
+    use crate::back::target::Target;
+    use crate::front::ast::Ast;
+    use crate::front::lexer::Lexer;
+    use crate::front::nodes::node::Node;
+    use crate::front::nodes::r#type::Type;
+    use crate::front::semantic::{SemanticAnalyzer, SemanticContext};
+    use crate::front::token::Token;
+    use crate::middle::ir::{IRContext, IRInstruction};
+    use crate::testing::{analyze, lower, parse, parse_with_errors, run};
+
+    /// The number of children of every node, in pre-order, as a structural fingerprint.
+    fn shape(node: &dyn Node, out: &mut Vec<usize>) {
+        let children = node.children();
+        out.push(children.len());
+        for child in children {
+            shape(child, out);
+        }
+    }
+
+    /// The shape of `ast` and the IR it lowers to after analysis.
+    fn compiled(ast: Box<Ast>) -> (Vec<usize>, Vec<IRInstruction>) {
+        let mut nodes = Vec::new();
+        shape(ast.as_ref(), &mut nodes);
+        let ast = SemanticAnalyzer::new(ast).analyze().0.unwrap();
+        (nodes, ast.ir(&mut IRContext::new(Target::X86_64)))
+    }
+
+    #[test]
+    fn expected_tokens_are_named_in_errors() {
+        let cases = [
+            ("fn main() { ret 1 }", "Missing token ';' after return expression"),
+            ("fn main() { x := true ? 1 2; }", "Missing token ':' in ternary expression"),
+            ("struct P x: i32 }", "Missing token '{' after struct name"),
+            ("fn f(a i32) {}", "Missing token ':' after parameter name"),
+            ("#[cfg(foo] fn main() {}", "Missing token ')' closing 'cfg'"),
+        ];
+        for (src, message) in cases {
+            let (_, errors) = parse_with_errors(src);
+            assert_eq!(errors, vec![message.to_string()], "for {:?}", src);
+        }
+
+        let mut lexer = Lexer::new("fn main() { x := 1 }");
+        let mut parser = Parser::streaming("main.lts".to_string(), &mut lexer);
+        parser.parse(&mut SemanticContext::new()).unwrap();
+        assert_eq!(
+            parser.into_errors()[0].to_string(),
+            "Missing token ';' after walrus declaration in file: main.lts on line 1 at position 21"
+        );
+    }
+
+    #[test]
+    fn parentheses_group_unless_they_hold_a_comma() {
+        assert_eq!(run("fn main() -> i32 { ret (1 + 2) * (3); }"), 9);
+
+        let mismatch = |value: &str| {
+            analyze(&format!("fn main() {{ x: bool = {}; }}", value)).err().unwrap()
+        };
+        assert_eq!(
+            mismatch("(1, true)"),
+            "Cannot assign a value of type (i32, bool) to 'x' of type bool; use an explicit `as` cast"
+        );
+        assert_eq!(
+            mismatch("(1,)"),
+            "Cannot assign a value of type (i32,) to 'x' of type bool; use an explicit `as` cast"
+        );
+        assert_eq!(
+            mismatch("()"),
+            "Cannot assign a value of type () to 'x' of type bool; use an explicit `as` cast"
+        );
+    }
+
+    #[test]
+    fn trailing_commas_are_accepted() {
+        let src = "fn add(a: i32, b: i32,) -> i32 { ret a + b; } fn main() -> i32 { ret add(1, 2,); }";
+        let call = lower(src).into_iter().find_map(|inst| match inst {
+            IRInstruction::Call { arguments, .. } => Some(arguments),
+            _ => None,
+        });
+        assert_eq!(call.map(|arguments| arguments.len()), Some(2));
+
+        let (_, errors) = parse_with_errors("fn add(a: i32, b: i32) -> i32 { ret a + b; } fn main() { add(1,, 2); }");
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn for_loops_need_a_declaration_and_semicolons() {
+        assert!(parse_with_errors("fn main() { for i := 0; i < 3; i = i + 1 {} }").1.is_empty());
+        assert!(parse_with_errors("fn main() { for i: i64 = 0; i < 3; i = i + 1 {} }").1.is_empty());
+        assert_eq!(
+            parse_with_errors("fn main() { for 0; i < 3; i = i + 1 {} }").1,
+            vec!["Syntax error: Expected a variable declaration to start the for loop".to_string()]
+        );
+        assert_eq!(
+            parse_with_errors("fn main() { for i := 0; i < 3 i = i + 1 {} }").1,
+            vec!["Syntax error: Expected ';' after the for loop condition".to_string()]
+        );
+    }
+
+    /// Parses the single function definition `src` directly, keeping its concrete node.
+    fn function(src: &str) -> FunctionDefinition {
+        let mut lexer = Lexer::new(src);
+        let mut parser = Parser::streaming("test.lts".to_string(), &mut lexer);
+        assert_eq!(parser.consume().unwrap().0, Token::Fn);
+        parser.parse_fn(&mut SemanticContext::new(), false).unwrap()
+    }
+
+    #[test]
+    fn parameters_take_every_type() {
+        let f = function("fn f(x: bool, y: f64, z: str, w: usize, p: Point) {}");
+        let types: Vec<Type> = f.parameters.iter().map(|param| param.r#type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                Type::basic("bool"),
+                Type::basic("f64"),
+                Type::basic("str"),
+                Type::basic("usize"),
+                Type::Custom("Point".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn return_types_take_every_type() {
+        assert_eq!(function("fn f() -> f64 { ret 1 as f64; }").return_type.0, Some(Type::basic("f64")));
+        assert_eq!(function("fn f() -> bool { ret true; }").return_type.0, Some(Type::basic("bool")));
+        assert_eq!(function("fn f() -> u64 { ret 1; }").return_type.0, Some(Type::basic("u64")));
+        assert_eq!(function("fn f() -> MyType {}").return_type.0, Some(Type::Custom("MyType".to_string())));
+        assert_eq!(function("fn f() {}").return_type.0, None);
+    }
+
+    /// The functions kept from `src` when parsed with the given `-D` flags.
+    fn configured(src: &str, defines: &[(&str, Option<&str>)]) -> Vec<String> {
+        let defines = defines
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.map(str::to_string)))
+            .collect();
+        let mut lexer = Lexer::new(src);
+        let mut parser = Parser::streaming("test.lts".to_string(), &mut lexer).with_defines(defines);
+        let ast = parser.parse(&mut SemanticContext::new()).unwrap();
+        assert!(parser.into_errors().is_empty());
+        ast.children
+            .iter()
+            .filter_map(|item| item.defined_function().map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn cfg_keeps_items_only_when_the_flag_is_defined() {
+        let src = "#[cfg(debug)] fn dbg() {} fn main() {}";
+        assert_eq!(configured(src, &[]), vec!["main"]);
+        assert_eq!(configured(src, &[("debug", None)]), vec!["dbg", "main"]);
+        assert_eq!(configured(src, &[("release", None)]), vec!["main"]);
+    }
+
+    #[test]
+    fn cfg_skips_items_without_a_body_up_to_their_semicolon() {
+        let src = "#[cfg(debug)] static g: i32 = 1; fn main() {} #[cfg(debug)] fn dbg() {}";
+        assert_eq!(configured(src, &[]), vec!["main"]);
+        assert_eq!(configured(src, &[("debug", None)]), vec!["main", "dbg"]);
+
+        let src = "#[cfg(debug)] static g: i32 = 1;\nfn main() -> i32 { ret g; }";
+        let (_, errors) = parse_with_errors(src);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(analyze(src).err().unwrap(), "Undefined variable: g");
+    }
+
+    #[test]
+    fn cfg_compares_flag_values() {
+        let src = "#[cfg(mode = \"fast\")] fn fast() {} #[cfg(mode = \"small\")] fn small() {}";
+        assert_eq!(configured(src, &[("mode", Some("fast"))]), vec!["fast"]);
+        assert_eq!(configured(src, &[("mode", Some("small"))]), vec!["small"]);
+        assert!(configured(src, &[("mode", None)]).is_empty());
+    }
+
+    #[test]
+    fn buffered_and_streaming_parsers_agree() {
+        let src = "static g: i32 = 1;
+            fn add(a: i32, b: i32) -> i32 { ret a + b; }
+            fn main() -> i32 {
+                x := add(g, 2);
+                for i := 0; i < 3; i = i + 1 { x = x * 2; }
+                ret x > 10 ? x : 0;
+            }";
+        let (tokens, errors) = Lexer::new(src).lex();
+        assert!(errors.is_empty());
+        let mut parser = Parser::new("test.lts".to_string(), tokens);
+        let buffered = parser.parse(&mut SemanticContext::new()).unwrap();
+        assert!(parser.into_errors().is_empty());
+
+        assert_eq!(compiled(buffered), compiled(parse(src)));
+    }
+
+    /// The functions defined by the items that parsed.
+    fn functions(src: &str) -> (Vec<String>, Vec<String>) {
+        let (ast, errors) = parse_with_errors(src);
+        let ast = ast.expect("top-level errors are recovered from");
+        let names = ast
+            .children
+            .iter()
+            .filter_map(|item| item.defined_function().map(str::to_string))
+            .collect();
+        (names, errors)
+    }
+
+    #[test]
+    fn error_in_a_function_body_resumes_at_the_next_item() {
+        let (names, errors) = functions("fn main() { a := 1 $ 2; } fn f() {}");
+        assert_eq!(names, vec!["f"]);
+        assert_eq!(
+            errors,
+            vec![
+                "Unknown character '$'".to_string(),
+                "Missing token ';' after walrus declaration".to_string(),
+            ]
+        );
+
+        let (names, errors) = functions("fn main() { r#  := 2; } fn f() {}");
+        assert_eq!(names, vec!["f"]);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn stray_top_level_tokens_are_reported() {
+        let (names, errors) = functions("x := 1; fn main() {} 42");
+        assert_eq!(names, vec!["main"]);
+        assert_eq!(
+            errors,
+            vec![
+                "Unexpected token 'Identifier(\"x\")'".to_string(),
+                "Unexpected token 'NumberLiteral(\"42\")'".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_primitive_type_token_maps_to_its_type() {
+        let primitives = [
+            (Token::I32, "i32"),
+            (Token::I64, "i64"),
+            (Token::U32, "u32"),
+            (Token::U64, "u64"),
+            (Token::Usize, "usize"),
+            (Token::F32, "f32"),
+            (Token::F64, "f64"),
+            (Token::Char, "char"),
+            (Token::Str, "str"),
+            (Token::Bool, "bool"),
+        ];
+        for (token, name) in primitives {
+            assert_eq!(token_to_type(&token), Some(Type::basic(name)));
+            assert!(matches!(Type::basic(name), Type::Primitive(_)), "{} is not a primitive", name);
+        }
+        assert_eq!(token_to_type(&Token::Identifier("i32".to_string())), None);
+    }
+
+    #[test]
+    fn every_type_position_accepts_f64() {
+        parse(
+            "static s: f64 = 0;
+             struct P { x: f64 }
+             fn f(x: f64) -> f64 {
+                 y: f64 = x;
+                 const z: f64 = x;
+                 ret 1 as f64;
+             }",
+        );
+    }
+}
@@ -1,73 +1,346 @@
 use std::collections::{HashMap, HashSet};
 
-use super::{ast::Ast, nodes::r#type::{FunctionType, StructType, Type}};
+use crate::middle::ir::Slot;
+
+use super::{ast::Ast, nodes::id::NodeId, nodes::r#type::{FunctionType, StructType, Type}, token::Position};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Symbol {
     Variable(Type),
+    /// An immutable binding introduced by `const`.
+    Const(Type),
+    /// A mutable module-level global introduced by `static`.
+    Static(Type),
     Function(FunctionType),
     Struct(StructType),
     // etc.
 }
 
+/// A symbol in a scope, with where it was declared when that is known.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub symbol: Symbol,
+    /// Set for declarations that analysis checks for shadowing; symbols the
+    /// parser registers ahead of analysis have none.
+    pub position: Option<Position>,
+    /// Where the IR keeps the value. A local is named after its declaration
+    /// unless that name is already visible, so a shadowing local gets a fresh `x.1`.
+    pub slot: Slot,
+}
+
 pub struct SemanticContext {
-    // Keyed by name (String) for ease of lookup.
-    pub symbol_table: HashMap<String, Symbol>,
-    pub current_scope: Vec<HashSet<String>>,
+    /// One symbol table per lexical scope, innermost last; the first is the global scope.
+    pub scopes: Vec<HashMap<String, Binding>>,
     pub current_function_return: Option<Type>,
+    /// Set while analyzing a function without a return annotation. The first
+    /// `ret` then fixes `current_function_return` and later ones must agree.
+    pub inferring_return: bool,
+    /// The number of loops around the statement being analyzed; `break` and `continue` need one.
+    pub loop_depth: usize,
+    pub lints: bool, // Whether opt-in lints are reported
+    /// The type of an integer literal with nothing else to go by, `i32` unless set with `--default-int`.
+    pub default_integer: Type,
+    /// Slots of locals declared without an initializer that are not yet definitely assigned.
+    pub uninitialized: HashSet<Slot>,
+    /// How many slots have been made for each shadowed name, to number the next one.
+    shadow_count: HashMap<String, usize>,
+    pub warnings: Vec<(String, Position)>,
+    /// Every function the file defines, collected before analysis starts. A user
+    /// function shadows the `len`/`chars` intrinsics even above its definition.
+    pub functions: HashSet<String>,
+    /// Always-available runtime functions, consulted after every scope; user definitions shadow them.
+    pub prelude: HashMap<String, Symbol>,
+    /// Inferred types of composite expressions, keyed by the `NodeId` the parser assigned.
+    pub type_cache: HashMap<NodeId, Type>,
+    pub type_cache_hits: usize,
 }
 
+/// The prelude functions as `(name, parameter types, return type)`.
+const PRELUDE: &[(&str, &[&str], &str)] = &[
+    ("print_int", &["i32"], "void"),
+    ("print_str", &["str"], "void"),
+];
+
 impl SemanticContext {
     pub fn new() -> Self {
         SemanticContext {
-            symbol_table: HashMap::new(),
-            current_scope: vec![HashSet::new()],
+            scopes: vec![HashMap::new()],
             current_function_return: None,
+            inferring_return: false,
+            loop_depth: 0,
+            lints: false,
+            default_integer: Type::basic("i32"),
+            uninitialized: HashSet::new(),
+            shadow_count: HashMap::new(),
+            warnings: Vec::new(),
+            functions: HashSet::new(),
+            prelude: PRELUDE
+                .iter()
+                .map(|(name, parameters, return_type)| {
+                    let signature = FunctionType {
+                        parameters: parameters.iter().map(|p| Type::basic(p)).collect(),
+                        return_type: Box::new(Type::basic(return_type)),
+                    };
+                    (name.to_string(), Symbol::Function(signature))
+                })
+                .collect(),
+            type_cache: HashMap::new(),
+            type_cache_hits: 0,
+        }
+    }
+
+    /// Records an opt-in lint warning, ignored unless lints are enabled.
+    pub fn lint(&mut self, message: String, position: &Position) {
+        if self.lints {
+            self.warnings.push((message, position.clone()));
         }
     }
 
     pub fn enter_scope(&mut self) {
-        self.current_scope.push(HashSet::new());
+        self.scopes.push(HashMap::new());
     }
 
+    /// Leaves the innermost scope, dropping its symbols so any shadowed outer ones are visible again.
     pub fn exit_scope(&mut self) {
-        self.current_scope.pop();
+        if let Some(scope) = self.scopes.pop() {
+            for binding in scope.values() {
+                self.uninitialized.remove(&binding.slot);
+            }
+        }
     }
 
+    /// Declares `id` in the innermost scope, shadowing any outer symbol of the same name.
     pub fn add_symbol(&mut self, id: &str, symbol: Symbol) {
-        self.symbol_table.insert(id.to_string(), symbol);
-        if let Some(scope) = self.current_scope.last_mut() {
-            scope.insert(id.to_string());
-        }
+        let slot = Slot::Local(id.to_string());
+        self.bind(id, Binding { symbol, position: None, slot });
     }
 
-    pub fn lookup(&self, id: &str) -> Option<&Symbol> {
-        for scope in self.current_scope.iter().rev() {
-            if scope.contains(id) {
-                return self.symbol_table.get(id);
+    /// Declares the binding `id` written at `position` and returns its slot,
+    /// linting when it shadows a declaration from an enclosing scope. Names
+    /// starting with `_` opt out of the lint.
+    pub fn declare(&mut self, id: &str, symbol: Symbol, position: &Position) -> Slot {
+        let shadowed = self.scopes[..self.scopes.len() - 1]
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(id))
+            .and_then(|binding| binding.position.clone());
+        if let Some(outer) = shadowed {
+            if !id.starts_with('_') {
+                self.lint(
+                    format!(
+                        "'{}' shadows the declaration on line {} at position {}",
+                        id, outer.line, outer.index
+                    ),
+                    position,
+                );
             }
         }
-        None
+
+        let slot = match (&symbol, self.resolve(id)) {
+            (Symbol::Static(_), _) => Slot::Static(id.to_string()),
+            (_, Some(_)) => {
+                let count = self.shadow_count.entry(id.to_string()).or_default();
+                *count += 1;
+                Slot::Local(format!("{}.{}", id, count))
+            }
+            (_, None) => Slot::Local(id.to_string()),
+        };
+        self.bind(id, Binding { symbol, position: Some(position.clone()), slot: slot.clone() });
+        slot
+    }
+
+    fn bind(&mut self, id: &str, binding: Binding) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(id.to_string(), binding);
+        }
+    }
+
+    /// Looks `id` up in the innermost scope only, to detect redeclarations.
+    pub fn lookup_current(&self, id: &str) -> Option<&Symbol> {
+        self.scopes.last().and_then(|scope| scope.get(id)).map(|binding| &binding.symbol)
+    }
+
+    pub fn lookup(&self, id: &str) -> Option<&Symbol> {
+        self.lookup_declared(id).or_else(|| self.prelude.get(id))
+    }
+
+    /// Like `lookup`, but ignores the prelude.
+    pub fn lookup_declared(&self, id: &str) -> Option<&Symbol> {
+        self.resolve(id).map(|binding| &binding.symbol)
+    }
+
+    /// Finds the binding `id` refers to, walking the scopes from the innermost out.
+    pub fn resolve(&self, id: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(id))
     }
 }
 
+/// Analyzes an AST in a context of its own, configured with the `with_*` methods.
 pub struct SemanticAnalyzer {
     ast: Box<Ast>,
+    ctx: SemanticContext,
+    /// Whether the parser recovered from errors, so items may be missing.
+    recovered: bool,
 }
 
 impl SemanticAnalyzer {
     pub fn new(ast: Box<Ast>) -> SemanticAnalyzer {
-        SemanticAnalyzer { ast }
+        SemanticAnalyzer {
+            ast,
+            ctx: SemanticContext::new(),
+            recovered: false,
+        }
+    }
+
+    /// Reports the opt-in lints as warnings.
+    pub fn with_lints(mut self, lints: bool) -> Self {
+        self.ctx.lints = lints;
+        self
+    }
+
+    /// Marks the AST as recovered from errors already reported. Recovery may
+    /// have dropped `main`, so its absence is not reported again.
+    pub fn with_recovered_errors(mut self, recovered: bool) -> Self {
+        self.recovered = recovered;
+        self
     }
 
-    pub fn analyze(self, ctx: &mut SemanticContext) -> Result<Box<Ast>, String> {
+    /// Sets the type of integer literals that have no other type to take on.
+    pub fn with_default_integer(mut self, default_integer: Type) -> Self {
+        self.ctx.default_integer = default_integer;
+        self
+    }
+
+    /// Analyzes the AST, returning the context alongside the result so later
+    /// phases can read its symbols, warnings and caches, even on error.
+    pub fn analyze(self) -> (Result<Box<Ast>, String>, SemanticContext) {
+        let SemanticAnalyzer { ast, mut ctx, recovered } = self;
+        let result = Self::analyze_items(&ast, &mut ctx, recovered).map(|()| ast);
+        (result, ctx)
+    }
+
+    fn analyze_items(ast: &Ast, ctx: &mut SemanticContext, recovered: bool) -> Result<(), String> {
+        ctx.functions.extend(
+            ast.children
+                .iter()
+                .filter_map(|node| node.defined_function().map(str::to_string)),
+        );
+
         // Analyze each child node of the AST
-        for node in self.ast.children.iter() {
+        for node in ast.children.iter() {
             node.analyze(ctx)?;
         }
 
         // dbg!(&ctx.symbol_table);
 
-        Ok(self.ast)
+        if recovered {
+            return Ok(());
+        }
+        Self::check_entry_point(ctx)
+    }
+
+    /// Ensures the program has a `main` taking no parameters and returning `void` or `i32`.
+    fn check_entry_point(ctx: &SemanticContext) -> Result<(), String> {
+        let main = match ctx.lookup("main") {
+            Some(Symbol::Function(main)) => main,
+            Some(_) => return Err("`main` must be a function".to_string()),
+            None => return Err("No `main` function found; every program needs an entry point".to_string()),
+        };
+
+        let returns_valid =
+            *main.return_type == Type::basic("void") || *main.return_type == Type::basic("i32");
+        if !main.parameters.is_empty() || !returns_valid {
+            return Err(format!(
+                "`main` must have the signature `fn() -> void` or `fn() -> i32`, found `{}`",
+                Type::Function(main.clone())
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SemanticAnalyzer;
+    use crate::front::nodes::r#type::Type;
+    use crate::middle::ir::IRInstruction;
+    use crate::testing::{analyze, analyze_with_context, lower, parse};
+
+    /// Analyzes `src` with unannotated integer literals typed `default_integer`.
+    fn analyze_with_default(src: &str, default_integer: &str) -> Result<(), String> {
+        let analyzer = SemanticAnalyzer::new(parse(src)).with_default_integer(Type::basic(default_integer));
+        analyzer.analyze().0.map(|_| ())
+    }
+
+    #[test]
+    fn unannotated_literals_take_the_configured_default() {
+        let src = "fn main() { x := 1; y: i32 = x; }";
+        assert_eq!(
+            analyze_with_default(src, "i64").err().unwrap(),
+            "Cannot assign a value of type i64 to 'y' of type i32; use an explicit `as` cast"
+        );
+        assert!(analyze_with_default("fn main() { x := 5000000000; y: i64 = x; }", "i64").is_ok());
+        // The default is i32 unless configured.
+        assert!(analyze(src).is_ok());
+    }
+
+    #[test]
+    fn annotations_win_over_the_default_integer() {
+        assert!(analyze_with_default("fn main() { x: i32 = 1; y: i32 = x; }", "i64").is_ok());
+        assert!(analyze_with_default("fn main() { x: u64 = 1; y: u64 = x; }", "i64").is_ok());
+    }
+
+    #[test]
+    fn composite_expression_types_are_cached_per_node() {
+        let (result, ctx) = analyze_with_context("fn main() { a := (1 + 2) * 3; b := (1 + 2) * 3 > 4 ? 1 : 0; }");
+        result.unwrap();
+        // Two binaries in `a`; two binaries, a comparison and a ternary in `b`.
+        assert_eq!(ctx.type_cache.len(), 6);
+        assert!(ctx.type_cache_hits > 0);
+    }
+
+    #[test]
+    fn missing_main_is_an_error() {
+        assert_eq!(
+            analyze("fn start() {}").err().unwrap(),
+            "No `main` function found; every program needs an entry point"
+        );
+        // A `main` dropped while recovering from a parse error is not reported again.
+        let recovered = SemanticAnalyzer::new(parse("fn start() {}")).with_recovered_errors(true);
+        assert!(recovered.analyze().0.is_ok());
+    }
+
+    #[test]
+    fn main_with_a_wrong_signature_is_an_error() {
+        assert_eq!(
+            analyze("fn main(argc: i32) {}").err().unwrap(),
+            "`main` must have the signature `fn() -> void` or `fn() -> i32`, found `fn(i32) -> void`"
+        );
+        assert!(analyze("fn main() -> bool { ret true; }").is_err());
+    }
+
+    #[test]
+    fn valid_main_is_accepted() {
+        assert!(analyze("fn main() {}").is_ok());
+        assert!(analyze("fn main() -> i32 { ret 0; }").is_ok());
+    }
+
+    #[test]
+    fn prelude_functions_need_no_declaration() {
+        let ir = lower("fn main() { print_int(42); print_str(\"hi\"); }");
+        let calls: Vec<(&str, usize)> = ir
+            .iter()
+            .filter_map(|inst| match inst {
+                IRInstruction::Call { function, arguments, .. } => Some((function.as_str(), arguments.len())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(calls, vec![("print_int", 1), ("print_str", 1)]);
+    }
+
+    #[test]
+    fn prelude_functions_are_type_checked() {
+        assert!(analyze("fn main() { print_int(\"hi\"); }").is_err());
+        assert!(analyze("fn main() { print_str(1, 2); }").is_err());
     }
 }
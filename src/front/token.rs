@@ -1,25 +1,31 @@
 #[derive(Clone, Debug, PartialEq)]
 pub enum Token {
-    Unknown(char),
     Eof,
 
     Identifier(String),
 
     Fn,
     Ret,
+    Const,
+    Static,
     Struct,
     Pub,
     Enum,
     Impl,
+    Where,
 
     If,
     Else,
     For,
     While,
+    Match,
+    Break,
+    Continue,
 
     NumberLiteral(String), // Stores both integers and floats as strings
     CharacterLiteral(char),
     StringLiteral(String),
+    BooleanLiteral(bool),
 
     Plus,
     Minus,
@@ -29,13 +35,32 @@ pub enum Token {
 
     Equal,
     Walrus,
+    As,
+    Not,
+    Compare, // `<=>`
+    EqEq,
+    NotEq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Caret,
+    ShiftLeft,
+    ShiftRight,
+    And, // `&&`
+    Or,  // `||`
 
     LPar,
     RPar,
     LCurl,
     RCurl,
+    LBracket,
+    RBracket,
 
     Arrow,
+    FatArrow,   // `=>`
+    DotDot,      // `..`
+    DotDotEqual, // `..=`
 
     I32,
     I64,
@@ -46,10 +71,14 @@ pub enum Token {
     F64,
     Char,
     Str,
+    Bool,
 
     Comma,
     Semicolon,
     Colon,
+    Question,
+    At,
+    Hash,
 }
 
 #[derive(Clone, Debug, Default)]
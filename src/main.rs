@@ -1,16 +1,21 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Read, Result};
 use std::path::Path;
+use std::time::{Duration, Instant};
 
+use config::Emit;
+use front::diagnostic::{Diagnostic, Phase};
 use front::nodes::node::Node;
 use front::semantic::{SemanticAnalyzer, SemanticContext};
-use front::token::Position;
+use middle::callgraph::CallGraph;
 use middle::ir::IRContext;
 
 mod back;
 mod config;
 mod front;
 mod middle;
+#[cfg(test)]
+mod testing;
 
 macro_rules! here {
     () => {
@@ -29,6 +34,35 @@ fn read_file_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
     Ok(contents)
 }
 
+fn print_pass_timings(timings: &[(&str, Duration)]) {
+    let total: Duration = timings.iter().map(|(_, time)| *time).sum();
+
+    eprintln!("\n{:<12} {:>12}", "Pass", "Time");
+    for (pass, time) in timings {
+        eprintln!("{:<12} {:>12.3?}", pass, time);
+    }
+    eprintln!("{:<12} {:>12.3?}", "total", total);
+}
+
+/// Writes an intermediate artifact to `<output>.<extension>` for `--keep-temps`.
+fn keep_temp(output: &str, extension: &str, contents: &str) {
+    let path = format!("{}.{}", output, extension);
+    match fs::write(&path, contents) {
+        Ok(()) => eprintln!("note: wrote {}", path),
+        Err(e) => eprintln!("Failed to write {}: {}", path, e),
+    }
+}
+
+fn print_diagnostics(diagnostics: &mut [Diagnostic], format: config::ErrorFormat, file: &str) {
+    Diagnostic::sort(diagnostics);
+    for diagnostic in diagnostics.iter() {
+        match format {
+            config::ErrorFormat::Human => eprintln!("{}", diagnostic),
+            config::ErrorFormat::Json => eprintln!("{}", diagnostic.to_json(file)),
+        }
+    }
+}
+
 fn main() {
     let config = config::PetalConfig::from_args();
     // dbg!(&config);
@@ -40,41 +74,129 @@ fn main() {
         }
     };
 
-    println!("\n{}", src);
+    if config.debug_mode {
+        println!("\n{}", src);
+    }
 
-    let lexer = front::lexer::Lexer::new(&src);
-    let tokens: Vec<(front::token::Token, Position)> = lexer.lex();
+    let mut timings: Vec<(&str, Duration)> = Vec::new();
+    let file = config.src.to_string_lossy().into_owned();
+
+    if config.keep_temps || config.prints(Emit::Tokens) {
+        // The parser lexes on demand, so the token list is produced by a separate lexing pass.
+        let (tokens, errors) = front::lexer::Lexer::new(&src).lex();
+        let dump: String = tokens
+            .iter()
+            .map(|(token, pos)| format!("{}:{} {:?}\n", pos.line, pos.index, token))
+            .collect();
+        if config.keep_temps {
+            keep_temp(&config.output_file_name, "tokens", &dump);
+        }
+        if config.prints(Emit::Tokens) {
+            print!("{}", dump);
+        }
 
-    /*
-    for (token, _) in &tokens {
-        println!("{:?}", token);
+        // Only the tokens were asked for, so stop before parsing.
+        if config.emit == Emit::Tokens {
+            let mut diagnostics: Vec<Diagnostic> = errors.into_iter().map(Diagnostic::from).collect();
+            print_diagnostics(&mut diagnostics, config.error_format, &file);
+            if diagnostics.iter().any(Diagnostic::is_error) {
+                std::process::exit(1);
+            }
+            return;
+        }
     }
-    */
 
+    // The parser only tracks which names are in scope; analysis builds its own context.
     let mut ctx = SemanticContext::new();
+    // Entries and hits of the analysis type cache, reported by `--time-passes`.
+    let mut type_cache = (0, 0);
+
+    // Tokens are lexed on demand while parsing, so the two passes are timed together.
+    let start = Instant::now();
+    let mut lexer = front::lexer::Lexer::new(&src);
+    let (parsed, parse_errors) = {
+        let mut parser = front::parser::Parser::streaming(file.clone(), &mut lexer)
+            .with_defines(config.defines.clone());
+        let parsed = parser.parse(&mut ctx);
+        (parsed, parser.into_errors())
+    };
+    timings.push(("lex+parse", start.elapsed()));
+
+    // The lexer skips bad characters, so later phases still run and report alongside it.
+    let mut diagnostics: Vec<Diagnostic> =
+        lexer.into_errors().into_iter().map(Diagnostic::from).collect();
+    diagnostics.extend(parse_errors.into_iter().map(Diagnostic::from));
 
-    let mut parser =
-        front::parser::Parser::new(config.src.clone().to_string_lossy().into_owned(), tokens);
-    match parser.parse(&mut ctx) {
+    match parsed {
         Ok(ast) => {
-            ast.display(0);
-            println!("");
+            if config.prints(Emit::Ast) {
+                ast.display(0);
+                println!();
+            }
+
+            let analyzer = SemanticAnalyzer::new(ast)
+                .with_recovered_errors(diagnostics.iter().any(Diagnostic::is_error))
+                .with_lints(config.lint)
+                .with_default_integer(config.default_integer.clone());
+
+            let start = Instant::now();
+            let (analyzed, analysis) = analyzer.analyze();
+            timings.push(("analyze", start.elapsed()));
+            type_cache = (analysis.type_cache.len(), analysis.type_cache_hits);
+
+            for (warning, pos) in &analysis.warnings {
+                diagnostics.push(Diagnostic::warning(
+                    Phase::Semantic,
+                    warning.clone(),
+                    Some(pos.clone()),
+                ));
+            }
 
-            let analyzer = SemanticAnalyzer::new(ast);
+            match analyzed {
+                Ok(analyzed_ast) if !diagnostics.iter().any(Diagnostic::is_error) => {
+                    // Stdout holds only the requested dumps; status goes to stderr as notes.
+                    if config.prints(Emit::CallGraph) {
+                        print!("{}", CallGraph::build(&analyzed_ast));
+                    }
+                    if config.prints(Emit::CallGraphDot) {
+                        print!("{}", CallGraph::build(&analyzed_ast).to_dot());
+                    }
 
-            match analyzer.analyze(&mut ctx) {
-                Ok(analyzed_ast) => {
-                    println!("Semantic analysis successful!");
-                    
-                    let mut ctx = IRContext::new();
-                    let ir = analyzed_ast.ir(&mut ctx);
+                    let start = Instant::now();
+                    let mut ctx = IRContext::new(config.target);
+                    let mut ir = analyzed_ast.ir(&mut ctx);
+                    middle::optimization::forward_stores(&mut ir);
+                    middle::optimization::remove_nops(&mut ir);
+                    timings.push(("ir-gen", start.elapsed()));
 
+                    let mut dump = String::new();
                     for inst in ir {
-                        println!("{:?}", inst);
+                        dump.push_str(&format!("{:?}\n", inst));
+                    }
+                    for (label, constant) in ctx.constants() {
+                        dump.push_str(&format!("{}: {:?}\n", label, constant));
+                    }
+                    for data in ctx.statics() {
+                        dump.push_str(&format!("{}: static {} ({} bytes)\n", data.name, data.value, data.size));
+                    }
+                    if config.prints(Emit::Ir) {
+                        print!("{}", dump);
+                    }
+
+                    if config.keep_temps {
+                        keep_temp(&config.output_file_name, "ir", &dump);
+                    }
+
+                    if config.emit == Emit::Asm {
+                        eprintln!(
+                            "note: no assembly written to {}; code generation is not implemented yet",
+                            config.output_file_name
+                        );
                     }
                 }
+                Ok(_) => {}
                 Err(e) => {
-                    eprintln!("Semantic analysis failed: {}", e);
+                    diagnostics.push(Diagnostic::error(Phase::Semantic, e, None));
                 }
             }
             
@@ -109,12 +231,23 @@ main:
             );
 
             if let Ok(_) = output_file.write_all(asm.as_bytes()) {
-                println!("Successfully wrote to .s file!");
+                eprintln!("note: wrote {}", s);
             }
             */
         }
         Err(e) => {
-            eprintln!("Parsing failed: {}", e);
+            diagnostics.push(Diagnostic::from(e));
         }
     }
+
+    print_diagnostics(&mut diagnostics, config.error_format, &file);
+
+    if config.time_passes {
+        print_pass_timings(&timings);
+        eprintln!("type cache: {} entries, {} hits", type_cache.0, type_cache.1);
+    }
+
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        std::process::exit(1);
+    }
 }
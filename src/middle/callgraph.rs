@@ -0,0 +1,101 @@
+use std::fmt;
+
+use crate::front::nodes::node::Node;
+
+/// The functions each function calls directly, in source order.
+pub struct CallGraph {
+    /// `(caller, callees)` for every function definition, each callee listed once.
+    pub edges: Vec<(String, Vec<String>)>,
+}
+
+impl CallGraph {
+    /// Builds the call graph by walking every function definition below `root`.
+    pub fn build(root: &dyn Node) -> Self {
+        let mut graph = CallGraph { edges: Vec::new() };
+        graph.visit(root);
+        graph
+    }
+
+    fn visit(&mut self, node: &dyn Node) {
+        match node.defined_function() {
+            Some(caller) => {
+                let mut callees = Vec::new();
+                for child in node.children() {
+                    Self::collect_calls(child, &mut callees);
+                }
+                self.edges.push((caller.to_string(), callees));
+            }
+            None => {
+                for child in node.children() {
+                    self.visit(child);
+                }
+            }
+        }
+    }
+
+    fn collect_calls(node: &dyn Node, callees: &mut Vec<String>) {
+        if let Some(callee) = node.called_function() {
+            if !callees.iter().any(|known| known == callee) {
+                callees.push(callee.to_string());
+            }
+        }
+        for child in node.children() {
+            Self::collect_calls(child, callees);
+        }
+    }
+
+    /// Renders the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph callgraph {\n");
+        for (caller, callees) in &self.edges {
+            dot.push_str(&format!("    \"{}\";\n", caller));
+            for callee in callees {
+                dot.push_str(&format!("    \"{}\" -> \"{}\";\n", caller, callee));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl fmt::Display for CallGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (caller, callees) in &self.edges {
+            writeln!(f, "{} → {}", caller, callees.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CallGraph;
+    use crate::testing::analyze;
+
+    fn graph(src: &str) -> CallGraph {
+        CallGraph::build(analyze(src).unwrap().as_ref())
+    }
+
+    #[test]
+    fn each_caller_lists_its_callees_once() {
+        let graph = graph("fn b() {} fn c() {} fn a() { b(); c(); b(); } fn main() { a(); }");
+        let edges: Vec<(&str, Vec<&str>)> = graph
+            .edges
+            .iter()
+            .map(|(caller, callees)| (caller.as_str(), callees.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![("b", vec![]), ("c", vec![]), ("a", vec!["b", "c"]), ("main", vec!["a"])]
+        );
+        assert_eq!(graph.to_string(), "b → \nc → \na → b, c\nmain → a\n");
+    }
+
+    #[test]
+    fn dot_output_has_one_edge_per_call() {
+        let dot = graph("fn b() -> i32 { ret 1; } fn a() { x := 1 + b(); } fn main() {}").to_dot();
+        assert!(dot.starts_with("digraph callgraph {\n"));
+        assert!(dot.contains("    \"a\" -> \"b\";\n"));
+        assert_eq!(dot.matches("->").count(), 1);
+    }
+}
@@ -1,10 +1,114 @@
+use crate::back::target::Target;
+use crate::front::nodes::r#type::Type;
+
+/// A value that lives in the read-only data section rather than as an immediate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Str(String),
+}
+
+/// A module-level static, laid out in `.data` by the code generator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticData {
+    pub name: String,
+    /// The width of the value in bytes on the target being compiled for.
+    pub size: usize,
+    pub value: i64,
+}
+
+/// Where a binding's value is kept, chosen during semantic analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Slot {
+    /// A function-local variable. A local shadowing a visible binding gets a
+    /// distinct name such as `x.1`.
+    Local(String),
+    /// A module-level static in the data section.
+    Static(String),
+}
+
+impl Slot {
+    /// The instruction loading the slot's value into `dest`.
+    pub fn load(&self, dest: String) -> IRInstruction {
+        match self {
+            Slot::Local(variable) => IRInstruction::LoadVariable {
+                dest,
+                variable: variable.clone(),
+            },
+            Slot::Static(global) => IRInstruction::LoadGlobal {
+                dest,
+                global: global.clone(),
+            },
+        }
+    }
+
+    /// The instruction storing `src` into the slot.
+    pub fn store(&self, src: String) -> IRInstruction {
+        match self {
+            Slot::Local(dest) => IRInstruction::Store {
+                dest: dest.clone(),
+                src,
+            },
+            Slot::Static(global) => IRInstruction::StoreGlobal {
+                global: global.clone(),
+                src,
+            },
+        }
+    }
+}
+
 pub struct IRContext {
-    temp_count: usize, // Counter for temporary register names
+    target: Target, // The architecture being compiled for
+    temp_count: usize,  // Counter for temporary register names
+    label_count: usize, // Counter for jump label names, restarted for every function
+    function: Option<String>, // The function being lowered, used to scope its labels
+    loops: Vec<(String, String)>, // (break, continue) labels of the enclosing loops, innermost last
+    constants: Vec<(String, Constant)>, // Constant pool, one entry per distinct value
+    statics: Vec<StaticData>, // Module-level statics and their initial values
 }
 
 impl IRContext {
-    pub fn new() -> Self {
-        IRContext { temp_count: 0 }
+    pub fn new(target: Target) -> Self {
+        IRContext {
+            target,
+            temp_count: 0,
+            label_count: 0,
+            function: None,
+            loops: Vec::new(),
+            constants: Vec::new(),
+            statics: Vec::new(),
+        }
+    }
+
+    // Returns the pool label for a constant, adding it only if the value is new
+    pub fn intern_constant(&mut self, constant: Constant) -> String {
+        if let Some((label, _)) = self.constants.iter().find(|(_, c)| *c == constant) {
+            return label.clone();
+        }
+        let label = format!("C{}", self.constants.len());
+        self.constants.push((label.clone(), constant));
+        label
+    }
+
+    pub fn constants(&self) -> &[(String, Constant)] {
+        &self.constants
+    }
+
+    // Records a module-level static, sized for the target and placed in `.data` by the code generator
+    pub fn define_static(&mut self, name: &str, static_type: &Type, value: i64) {
+        let size = self
+            .target
+            .size_of(static_type)
+            .expect("statics should have a primitive type");
+        self.statics.push(StaticData {
+            name: name.to_string(),
+            size,
+            value,
+        });
+    }
+
+    pub fn statics(&self) -> &[StaticData] {
+        &self.statics
     }
 
     // Allocate a new temporary register
@@ -13,17 +117,53 @@ impl IRContext {
         format!("t{}", self.temp_count) // Generates t1, t2, t3, ...
     }
 
-    // Helper functions to get previous temps for binary operations
+    // Starts lowering a new function; its labels are prefixed with its name
+    pub fn enter_function(&mut self, name: &str) {
+        self.function = Some(name.to_string());
+        self.label_count = 0;
+    }
+
+    // Allocate a new jump label named after the construct that branches to it
+    pub fn allocate_label(&mut self, kind: &str) -> String {
+        self.label_count += 1;
+        match &self.function {
+            Some(function) => format!(".L{}_{}_{}", function, kind, self.label_count), // .Lmain_if_then_1
+            None => format!(".L{}_{}", kind, self.label_count),
+        }
+    }
+
+    // Starts lowering a loop body; `break` jumps to the first label and `continue` to the second
+    pub fn enter_loop(&mut self, break_label: &str, continue_label: &str) {
+        self.loops.push((break_label.to_string(), continue_label.to_string()));
+    }
+
+    pub fn exit_loop(&mut self) {
+        self.loops.pop();
+    }
+
+    // The (break, continue) labels of the innermost loop being lowered
+    pub fn current_loop(&self) -> Option<&(String, String)> {
+        self.loops.last()
+    }
+
+    // Helper function to get the most recent temp
     pub fn get_last_temp(&self) -> String {
         format!("t{}", self.temp_count) // Last temp (e.g., t3)
     }
+}
 
-    pub fn get_second_last_temp(&self) -> String {
-        format!("t{}", self.temp_count - 1) // Second-to-last temp (e.g., t2)
-    }
+/// The relation tested by `IRInstruction::Cmp`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum IRInstruction {
     Add {
         dest: String,
@@ -35,10 +175,53 @@ pub enum IRInstruction {
         lhs: String,
         rhs: String,
     },
+    Mul {
+        dest: String,
+        lhs: String,
+        rhs: String,
+    },
+    Div {
+        dest: String,
+        lhs: String,
+        rhs: String,
+    },
+    Mod {
+        dest: String,
+        lhs: String,
+        rhs: String,
+    },
+    /// Sets `dest` to 1 when `lhs condition rhs` holds and to 0 otherwise.
+    Cmp {
+        dest: String,
+        condition: Condition,
+        lhs: String,
+        rhs: String,
+    },
+    /// Non-short-circuiting logical operators on boolean temporaries.
+    And {
+        dest: String,
+        lhs: String,
+        rhs: String,
+    },
+    Or {
+        dest: String,
+        lhs: String,
+        rhs: String,
+    },
     Load {
         dest: String,
         src: String,
     },
+    LoadConstant {
+        dest: String,
+        label: String,
+    },
+    Convert {
+        dest: String,
+        src: String,
+        from: String,
+        to: String,
+    },
     Store {
         dest: String,
         src: String,
@@ -52,15 +235,129 @@ pub enum IRInstruction {
         dest: String,
         variable: String,
     },
+    /// Reads the `index`th argument passed to the function being executed.
+    LoadArgument {
+        dest: String,
+        index: usize,
+    },
+    /// Reads the module-level static `global`.
+    LoadGlobal {
+        dest: String,
+        global: String,
+    },
+    /// Writes `src` to the module-level static `global`.
+    StoreGlobal {
+        global: String,
+        src: String,
+    },
+    Jump {
+        target: String,
+    },
     Label(String),
+    /// Jumps to `targets[index]`, or to `default` when `index` is outside the table
+    /// (negative indices included).
+    JumpTable {
+        index: String,
+        targets: Vec<String>,
+        default: String,
+    },
+    Call {
+        dest: String,
+        function: String,
+        arguments: Vec<String>,
+    },
+    /// Aborts execution; `location` labels a constant-pool string naming the source line.
+    Trap {
+        location: String,
+    },
     Ret(String),
+    /// Does nothing. Passes may overwrite an instruction with `Nop` instead of
+    /// removing it; `optimization::remove_nops` strips them before codegen.
+    Nop,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct IRFunction {
     pub id: String, // Change to 'IRIdentifier' later
     pub instructions: Vec<IRInstruction>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct IRModule {
     pub functions: Vec<IRFunction>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Constant, IRFunction, IRInstruction, StaticData};
+    use crate::back::target::Target;
+    use crate::testing::lower_for;
+
+    #[test]
+    fn repeated_constants_share_one_pool_entry() {
+        let (ir, ctx) = lower_for(
+            "fn main() { a: i64 = 5000000000; b: i64 = 5000000000; s := \"hi\"; t := \"hi\"; }",
+            Target::X86_64,
+        );
+        assert_eq!(
+            ctx.constants(),
+            &[
+                ("C0".to_string(), Constant::Int(5000000000)),
+                ("C1".to_string(), Constant::Str("hi".to_string())),
+            ]
+        );
+        let loads = ir.iter().filter(|inst| matches!(inst, IRInstruction::LoadConstant { .. }));
+        assert_eq!(loads.count(), 4);
+    }
+
+    #[test]
+    fn string_literals_load_from_the_constant_pool() {
+        let (ir, ctx) = lower_for("fn s() -> str { ret \"hi\"; } fn main() {}", Target::X86_64);
+        assert_eq!(ctx.constants(), &[("C0".to_string(), Constant::Str("hi".to_string()))]);
+        assert_eq!(
+            ir[..2],
+            [
+                IRInstruction::LoadConstant {
+                    dest: "t1".to_string(),
+                    label: "C0".to_string(),
+                },
+                IRInstruction::Ret("t1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn statics_are_sized_for_the_target() {
+        let src = "static count: usize = 3; static flag: bool = true; fn main() {}";
+        let size = |target| lower_for(src, target).1.statics().iter().map(|s| s.size).collect::<Vec<_>>();
+        assert_eq!(size(Target::X86_64), vec![8, 1]);
+        assert_eq!(size(Target::AArch64), vec![8, 1]);
+        assert_eq!(size(Target::Rp2040), vec![4, 1]);
+
+        let (_, ctx) = lower_for(src, Target::X86_64);
+        assert_eq!(
+            ctx.statics()[0],
+            StaticData {
+                name: "count".to_string(),
+                size: 8,
+                value: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn identical_functions_compare_equal() {
+        let build = || IRFunction {
+            id: "main".to_string(),
+            instructions: lower_for("fn main() -> i32 { x := 1 + 2; ret x; }", Target::X86_64).0,
+        };
+        assert_eq!(build(), build());
+        assert_eq!(build().clone(), build());
+
+        let other = IRFunction {
+            id: "main".to_string(),
+            instructions: lower_for("fn main() -> i32 { x := 1 + 3; ret x; }", Target::X86_64).0,
+        };
+        assert_ne!(build(), other);
+    }
+}
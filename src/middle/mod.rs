@@ -1,2 +1,3 @@
+pub mod callgraph;
 pub mod ir;
 pub mod optimization;
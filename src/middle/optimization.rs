@@ -1 +1,205 @@
+use std::collections::HashMap;
 
+use super::ir::IRInstruction;
+
+/// Removes every `IRInstruction::Nop`, keeping the order of the rest.
+pub fn remove_nops(instructions: &mut Vec<IRInstruction>) {
+    instructions.retain(|instruction| *instruction != IRInstruction::Nop);
+}
+
+/// Forwards stored values to later reloads and drops overwritten stores, one
+/// basic block at a time.
+///
+/// A `LoadVariable` of a slot stored earlier in the block becomes a `Load` from
+/// the stored temporary. A `Store` is replaced with a `Nop` when the same slot
+/// is stored again before anything reads it; stores still live at the end of a
+/// block are kept, since a successor may read them. Calls are treated like
+/// block boundaries, since the callee may read or write statics.
+pub fn forward_stores(instructions: &mut [IRInstruction]) {
+    // The temporary last stored to each slot in the current block.
+    let mut stored: HashMap<String, String> = HashMap::new();
+    // The index of each store that nothing in the block has read yet.
+    let mut unread: HashMap<String, usize> = HashMap::new();
+
+    for index in 0..instructions.len() {
+        match &instructions[index] {
+            IRInstruction::Label(_)
+            | IRInstruction::Jump { .. }
+            | IRInstruction::Branch { .. }
+            | IRInstruction::JumpTable { .. }
+            | IRInstruction::Ret(_)
+            | IRInstruction::Call { .. } => {
+                stored.clear();
+                unread.clear();
+            }
+            IRInstruction::Store { dest, src } => {
+                let (dest, src) = (dest.clone(), src.clone());
+                unread.retain(|slot, _| !reads(&instructions[index], slot));
+                if let Some(overwritten) = unread.insert(dest.clone(), index) {
+                    instructions[overwritten] = IRInstruction::Nop;
+                }
+                stored.insert(dest, src);
+            }
+            IRInstruction::LoadVariable { dest, variable } => match stored.get(variable) {
+                Some(src) => {
+                    instructions[index] = IRInstruction::Load {
+                        dest: dest.clone(),
+                        src: src.clone(),
+                    };
+                }
+                None => {
+                    unread.remove(variable);
+                }
+            },
+            instruction => unread.retain(|slot, _| !reads(instruction, slot)),
+        }
+    }
+}
+
+/// Whether `instruction` uses the value named `name` as an operand.
+fn reads(instruction: &IRInstruction, name: &str) -> bool {
+    match instruction {
+        IRInstruction::Add { lhs, rhs, .. }
+        | IRInstruction::Sub { lhs, rhs, .. }
+        | IRInstruction::Mul { lhs, rhs, .. }
+        | IRInstruction::Div { lhs, rhs, .. }
+        | IRInstruction::Mod { lhs, rhs, .. }
+        | IRInstruction::Cmp { lhs, rhs, .. }
+        | IRInstruction::And { lhs, rhs, .. }
+        | IRInstruction::Or { lhs, rhs, .. } => lhs == name || rhs == name,
+        IRInstruction::Load { src, .. }
+        | IRInstruction::Store { src, .. }
+        | IRInstruction::StoreGlobal { src, .. }
+        | IRInstruction::Convert { src, .. } => src == name,
+        IRInstruction::LoadVariable { variable, .. } => variable == name,
+        IRInstruction::Branch { condition, .. } => condition == name,
+        IRInstruction::JumpTable { index, .. } => index == name,
+        IRInstruction::Call { arguments, .. } => arguments.iter().any(|argument| argument == name),
+        IRInstruction::Ret(value) => value == name,
+        IRInstruction::LoadConstant { .. }
+        | IRInstruction::LoadGlobal { .. }
+        | IRInstruction::LoadArgument { .. }
+        | IRInstruction::Jump { .. }
+        | IRInstruction::Label(_)
+        | IRInstruction::Trap { .. }
+        | IRInstruction::Nop => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{forward_stores, remove_nops};
+    use crate::middle::ir::IRInstruction;
+
+    fn load(dest: &str, src: &str) -> IRInstruction {
+        IRInstruction::Load {
+            dest: dest.to_string(),
+            src: src.to_string(),
+        }
+    }
+
+    fn store(dest: &str, src: &str) -> IRInstruction {
+        IRInstruction::Store {
+            dest: dest.to_string(),
+            src: src.to_string(),
+        }
+    }
+
+    fn load_variable(dest: &str, variable: &str) -> IRInstruction {
+        IRInstruction::LoadVariable {
+            dest: dest.to_string(),
+            variable: variable.to_string(),
+        }
+    }
+
+    #[test]
+    fn reloads_become_moves_from_the_stored_temporary() {
+        let mut instructions = vec![
+            load("t1", "1"),
+            store("x", "t1"),
+            load_variable("t2", "x"),
+            IRInstruction::Ret("t2".to_string()),
+        ];
+        forward_stores(&mut instructions);
+        assert_eq!(
+            instructions,
+            vec![load("t1", "1"), store("x", "t1"), load("t2", "t1"), IRInstruction::Ret("t2".to_string())]
+        );
+    }
+
+    #[test]
+    fn stores_overwritten_before_a_read_are_dropped() {
+        let mut instructions = vec![store("x", "t1"), store("x", "t2"), load_variable("t3", "x")];
+        forward_stores(&mut instructions);
+        assert_eq!(instructions, vec![IRInstruction::Nop, store("x", "t2"), load("t3", "t2")]);
+
+        // A forwarded reload no longer reads the slot, so it doesn't keep the first store alive.
+        let mut instructions = vec![
+            store("x", "t1"),
+            load_variable("t2", "x"),
+            store("x", "t3"),
+            load_variable("t4", "x"),
+        ];
+        forward_stores(&mut instructions);
+        assert_eq!(
+            instructions,
+            vec![IRInstruction::Nop, load("t2", "t1"), store("x", "t3"), load("t4", "t3")]
+        );
+
+        // Any other use of the slot between the stores keeps the first one.
+        let add = IRInstruction::Add {
+            dest: "t2".to_string(),
+            lhs: "x".to_string(),
+            rhs: "1".to_string(),
+        };
+        let mut instructions = vec![store("x", "t1"), add.clone(), store("x", "t3")];
+        forward_stores(&mut instructions);
+        assert_eq!(instructions, vec![store("x", "t1"), add, store("x", "t3")]);
+    }
+
+    #[test]
+    fn block_boundaries_and_calls_stop_forwarding() {
+        let call = IRInstruction::Call {
+            dest: "t2".to_string(),
+            function: "f".to_string(),
+            arguments: Vec::new(),
+        };
+        for boundary in [IRInstruction::Label(".L1".to_string()), call] {
+            let mut instructions = vec![
+                store("x", "t1"),
+                boundary.clone(),
+                store("x", "t3"),
+                load_variable("t4", "x"),
+                load_variable("t5", "y"),
+            ];
+            forward_stores(&mut instructions);
+            // The first store may be read after the boundary, so it is kept.
+            assert_eq!(
+                instructions,
+                vec![store("x", "t1"), boundary, store("x", "t3"), load("t4", "t3"), load_variable("t5", "y")]
+            );
+        }
+    }
+
+    #[test]
+    fn nops_are_removed_in_order() {
+        let mut instructions = vec![
+            IRInstruction::Nop,
+            load("t1", "1"),
+            IRInstruction::Nop,
+            IRInstruction::Nop,
+            load("t2", "t1"),
+            IRInstruction::Ret("t2".to_string()),
+            IRInstruction::Nop,
+        ];
+        remove_nops(&mut instructions);
+        assert_eq!(
+            instructions,
+            vec![load("t1", "1"), load("t2", "t1"), IRInstruction::Ret("t2".to_string())]
+        );
+
+        let mut only_nops = vec![IRInstruction::Nop, IRInstruction::Nop];
+        remove_nops(&mut only_nops);
+        assert!(only_nops.is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+//! Helpers shared by the unit tests: compiling Lotus snippets through the
+//! front end and running the resulting IR.
+
+use std::collections::HashMap;
+
+use crate::back::target::Target;
+use crate::front::ast::Ast;
+use crate::front::lexer::Lexer;
+use crate::front::nodes::node::Node;
+use crate::front::parser::Parser;
+use crate::front::semantic::{SemanticAnalyzer, SemanticContext};
+use crate::middle::ir::{Condition, Constant, IRContext, IRInstruction};
+
+/// Parses `src`, returning the AST and every lexer and parser error message.
+pub fn parse_with_errors(src: &str) -> (Option<Box<Ast>>, Vec<String>) {
+    let mut ctx = SemanticContext::new();
+    let mut lexer = Lexer::new(src);
+    let (parsed, parse_errors) = {
+        let mut parser = Parser::streaming("test.lts".to_string(), &mut lexer);
+        let parsed = parser.parse(&mut ctx);
+        (parsed, parser.into_errors())
+    };
+
+    let mut errors: Vec<String> = lexer.into_errors().into_iter().map(|e| e.message).collect();
+    errors.extend(parse_errors.iter().map(|e| e.message()));
+    match parsed {
+        Ok(ast) => (Some(ast), errors),
+        Err(e) => {
+            errors.push(e.message());
+            (None, errors)
+        }
+    }
+}
+
+/// Parses `src`, failing the test on any lexer or parser error.
+pub fn parse(src: &str) -> Box<Ast> {
+    match parse_with_errors(src) {
+        (Some(ast), errors) if errors.is_empty() => ast,
+        (_, errors) => panic!("failed to parse {:?}: {:?}", src, errors),
+    }
+}
+
+/// Parses and analyzes `src` with the default settings, keeping the context
+/// so tests can read the symbols and caches analysis left behind.
+pub fn analyze_with_context(src: &str) -> (Result<Box<Ast>, String>, SemanticContext) {
+    SemanticAnalyzer::new(parse(src)).analyze()
+}
+
+/// Parses and analyzes `src` with the default settings.
+pub fn analyze(src: &str) -> Result<Box<Ast>, String> {
+    analyze_with_context(src).0
+}
+
+/// Analyzes `src` with lints enabled, returning each warning with its line.
+pub fn lints(src: &str) -> Vec<(String, usize)> {
+    let (result, ctx) = SemanticAnalyzer::new(parse(src)).with_lints(true).analyze();
+    result.unwrap_or_else(|e| panic!("failed to analyze {:?}: {}", src, e));
+    ctx.warnings.into_iter().map(|(message, pos)| (message, pos.line)).collect()
+}
+
+/// Analyzes `src` and lowers it for `target`, without running any optimization
+/// pass, returning the context that holds the constant pool and statics.
+pub fn lower_for(src: &str, target: Target) -> (Vec<IRInstruction>, IRContext) {
+    let ast = analyze(src).unwrap_or_else(|e| panic!("failed to analyze {:?}: {}", src, e));
+    let mut ctx = IRContext::new(target);
+    let instructions = ast.ir(&mut ctx);
+    (instructions, ctx)
+}
+
+/// Analyzes `src` and lowers it for x86-64, without running any optimization pass.
+pub fn lower(src: &str) -> Vec<IRInstruction> {
+    lower_for(src, Target::X86_64).0
+}
+
+/// Compiles `src` and interprets its `main`, returning the value `main` returns.
+pub fn run(src: &str) -> i64 {
+    let ast = analyze(src).unwrap_or_else(|e| panic!("failed to analyze {:?}: {}", src, e));
+    let mut ctx = IRContext::new(Target::X86_64);
+    let mut functions = HashMap::new();
+    for item in &ast.children {
+        let instructions = item.ir(&mut ctx);
+        if let Some(name) = item.defined_function() {
+            functions.insert(name.to_string(), instructions);
+        }
+    }
+
+    let mut interpreter = Interpreter {
+        functions: &functions,
+        constants: ctx.constants(),
+        globals: ctx.statics().iter().map(|data| (data.name.clone(), data.value)).collect(),
+        steps: 0,
+    };
+    interpreter.call("main", &[])
+}
+
+/// Executes IR with the meaning the code generator is meant to give it, so
+/// lowering can be tested by what a program computes.
+struct Interpreter<'a> {
+    functions: &'a HashMap<String, Vec<IRInstruction>>,
+    constants: &'a [(String, Constant)],
+    /// The current value of every static, shared by all calls.
+    globals: HashMap<String, i64>,
+    /// Instructions executed so far, bounded so a miscompiled loop fails the test.
+    steps: usize,
+}
+
+impl Interpreter<'_> {
+    fn call(&mut self, function: &str, arguments: &[i64]) -> i64 {
+        let functions = self.functions;
+        let instructions = &functions[function];
+        let labels: HashMap<&str, usize> = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, inst)| match inst {
+                IRInstruction::Label(label) => Some((label.as_str(), index)),
+                _ => None,
+            })
+            .collect();
+
+        let mut values: HashMap<String, i64> = HashMap::new();
+        let mut pc = 0;
+        while let Some(inst) = instructions.get(pc) {
+            self.steps += 1;
+            assert!(self.steps < 100_000, "{} did not terminate", function);
+            pc += 1;
+
+            let value = |operand: &str| -> i64 {
+                operand
+                    .parse()
+                    .unwrap_or_else(|_| *values.get(operand).unwrap_or_else(|| panic!("{} read before written", operand)))
+            };
+            let binary = |lhs: &str, rhs: &str, op: fn(i64, i64) -> i64| op(value(lhs), value(rhs));
+
+            let (dest, result) = match inst {
+                IRInstruction::Add { dest, lhs, rhs } => (dest, binary(lhs, rhs, i64::wrapping_add)),
+                IRInstruction::Sub { dest, lhs, rhs } => (dest, binary(lhs, rhs, i64::wrapping_sub)),
+                IRInstruction::Mul { dest, lhs, rhs } => (dest, binary(lhs, rhs, i64::wrapping_mul)),
+                IRInstruction::Div { dest, lhs, rhs } => (dest, binary(lhs, rhs, i64::wrapping_div)),
+                IRInstruction::Mod { dest, lhs, rhs } => (dest, binary(lhs, rhs, i64::wrapping_rem)),
+                IRInstruction::Cmp { dest, condition, lhs, rhs } => {
+                    let (lhs, rhs) = (value(lhs), value(rhs));
+                    let holds = match condition {
+                        Condition::Equal => lhs == rhs,
+                        Condition::NotEqual => lhs != rhs,
+                        Condition::Less => lhs < rhs,
+                        Condition::Greater => lhs > rhs,
+                        Condition::LessEqual => lhs <= rhs,
+                        Condition::GreaterEqual => lhs >= rhs,
+                    };
+                    (dest, holds as i64)
+                }
+                IRInstruction::And { dest, lhs, rhs } => (dest, (value(lhs) != 0 && value(rhs) != 0) as i64),
+                IRInstruction::Or { dest, lhs, rhs } => (dest, (value(lhs) != 0 || value(rhs) != 0) as i64),
+                IRInstruction::Load { dest, src } | IRInstruction::Store { dest, src } => (dest, value(src)),
+                IRInstruction::Convert { dest, src, .. } => (dest, value(src)),
+                IRInstruction::LoadVariable { dest, variable } => (dest, value(variable)),
+                IRInstruction::LoadGlobal { dest, global } => {
+                    (dest, *self.globals.get(global).unwrap_or_else(|| panic!("unknown static {}", global)))
+                }
+                IRInstruction::StoreGlobal { global, src } => {
+                    let src = value(src);
+                    self.globals.insert(global.clone(), src);
+                    continue;
+                }
+                IRInstruction::LoadConstant { dest, label } => {
+                    match self.constants.iter().find(|(name, _)| name == label) {
+                        Some((_, Constant::Int(constant))) => (dest, *constant),
+                        // Strings have no runtime representation yet; only their presence matters.
+                        Some((_, Constant::Str(_))) => (dest, 0),
+                        None => panic!("unknown constant {}", label),
+                    }
+                }
+                IRInstruction::Call { dest, function, arguments } => {
+                    let arguments: Vec<i64> = arguments.iter().map(|argument| value(argument)).collect();
+                    (dest, self.call(function, &arguments))
+                }
+                IRInstruction::LoadArgument { dest, index } => (dest, arguments[*index]),
+                IRInstruction::Branch { condition, true_label, false_label } => {
+                    let target = if value(condition) != 0 { true_label } else { false_label };
+                    pc = labels[target.as_str()];
+                    continue;
+                }
+                IRInstruction::Jump { target } => {
+                    pc = labels[target.as_str()];
+                    continue;
+                }
+                IRInstruction::JumpTable { index, targets, default } => {
+                    let target = usize::try_from(value(index))
+                        .ok()
+                        .and_then(|index| targets.get(index))
+                        .unwrap_or(default);
+                    pc = labels[target.as_str()];
+                    continue;
+                }
+                IRInstruction::Trap { location } => panic!("{} trapped at {}", function, location),
+                IRInstruction::Ret(src) => return value(src),
+                IRInstruction::Label(_) | IRInstruction::Nop => continue,
+            };
+            values.insert(dest.clone(), result);
+        }
+        0
+    }
+}
@@ -0,0 +1,261 @@
+//! End-to-end tests that run the `petal` binary on small programs.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Writes `src` to a fresh directory named after the test and returns the source path.
+fn write_source(test: &str, src: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("petal-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("main.lts");
+    fs::write(&path, src).unwrap();
+    path
+}
+
+/// Compiles `src` with the given flags.
+fn petal(test: &str, src: &str, args: &[&str]) -> Output {
+    let path = write_source(test, src);
+    Command::new(env!("CARGO_BIN_EXE_petal"))
+        .arg(&path)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+const VALID: &str = "fn main() -> i32 {\n    x := 1;\n    ret x + 1;\n}\n";
+
+#[test]
+fn time_passes_reports_every_phase() {
+    let output = petal("time-passes", VALID, &["--time-passes"]);
+    assert_eq!(stdout(&output), "");
+    let report = stderr(&output);
+    for phase in ["lex+parse", "analyze", "ir-gen", "total"] {
+        assert!(
+            report.lines().any(|line| line.starts_with(phase)),
+            "no {} line in:\n{}",
+            phase,
+            report
+        );
+    }
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// The diagnostics on stderr, without the `note:` lines saying what was produced.
+fn diagnostics(output: &Output) -> String {
+    stderr(output)
+        .lines()
+        .filter(|line| !line.starts_with("note: "))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[test]
+fn diagnostics_from_every_phase_are_sorted_by_line() {
+    let src = "fn f() -> i32 where T: Copy { ret 1; }\nfn g() { x := 1 }\nfn main() { y := 2 $; }\n";
+    let output = petal("sorted-diagnostics", src, &[]);
+    let diagnostics = stderr(&output);
+    let phases: Vec<&str> = diagnostics
+        .lines()
+        .map(|line| line.split(':').next().unwrap())
+        .collect();
+    assert_eq!(phases, vec!["Warning (semantic)", "Error (parse)", "Error (lex)"]);
+}
+
+#[test]
+fn keep_temps_writes_tokens_and_ir_next_to_the_output() {
+    let dir = write_source("keep-temps", VALID).parent().unwrap().to_path_buf();
+    let out = dir.join("prog");
+    let output = petal("keep-temps", VALID, &["--keep-temps", "-o", out.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let tokens = fs::read_to_string(dir.join("prog.tokens")).unwrap();
+    assert!(tokens.lines().next().unwrap().ends_with("Fn"), "{}", tokens);
+    let ir = fs::read_to_string(dir.join("prog.ir")).unwrap();
+    assert!(ir.lines().any(|line| line.starts_with("Ret(")), "{}", ir);
+
+    let notes = stderr(&output);
+    for extension in ["tokens", "ir"] {
+        let note = format!("note: wrote {}.{}", out.display(), extension);
+        assert!(notes.lines().any(|line| line == note), "no {:?} in:\n{}", note, notes);
+    }
+}
+
+#[test]
+fn ast_dump_marks_public_items() {
+    let src = "pub fn f() {}\nfn main() {}\npub struct S { x: i32 }\nstruct T { y: i32 }\n";
+    let output = petal("pub-items", src, &["--emit", "ast"]);
+    let ast = stdout(&output);
+    for item in ["FnDef: pub `f`", "FnDef: `main`", "StructDef: pub `S`", "StructDef: `T`"] {
+        assert!(ast.contains(item), "no {:?} in:\n{}", item, ast);
+    }
+}
+
+#[test]
+fn define_flags_select_cfg_items() {
+    let src = "#[cfg(debug)]\nfn dbg() {}\nfn main() {\n    dbg();\n}\n";
+    let without = petal("cfg-off", src, &[]);
+    assert!(stderr(&without).contains("Call to undefined function 'dbg'"), "{}", stderr(&without));
+
+    let with = petal("cfg-on", src, &["-D", "debug"]);
+    assert!(diagnostics(&with).is_empty(), "{}", stderr(&with));
+    assert!(with.status.success());
+}
+
+#[test]
+fn valid_program_is_analyzed_end_to_end() {
+    let output = petal("analyze", VALID, &["--time-passes"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    let report = stderr(&output);
+    assert!(
+        report.starts_with("note: no assembly written to a.out; code generation is not implemented yet\n"),
+        "{}",
+        report
+    );
+    // The context analysis returns still holds the type of `x + 1`.
+    assert!(report.contains("type cache: 1 entries"), "{}", report);
+
+    let src = "fn main() -> i32 {\n    x := 1;\n    c := true;\n    if c {\n        x := 2;\n    }\n    ret x;\n}\n";
+    let output = petal("analyze-lints", src, &["--lint"]);
+    assert!(output.status.success());
+    assert!(stderr(&output).contains("'x' shadows the declaration on line 2"), "{}", stderr(&output));
+}
+
+#[test]
+fn json_error_format_prints_one_object_per_diagnostic() {
+    let output = petal("json-errors", "fn main() {\n    y := 2 $;\n}\n", &["--error-format", "json"]);
+    let diagnostics = stderr(&output);
+    let lines: Vec<&str> = diagnostics.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", diagnostics);
+    assert!(lines[0].starts_with('{') && lines[0].ends_with('}'), "{}", lines[0]);
+    for field in [
+        r#""severity":"error""#,
+        r#""phase":"lex""#,
+        r#""message":"Unknown character '$'""#,
+        r#""line":2"#,
+    ] {
+        assert!(lines[0].contains(field), "no {} in {}", field, lines[0]);
+    }
+}
+
+#[test]
+fn default_int_sets_the_type_of_unannotated_literals() {
+    let src = "fn main() {\n    x := 1;\n    y: i32 = x;\n}\n";
+    assert!(diagnostics(&petal("default-int-i32", src, &[])).is_empty());
+    let diagnostics = stderr(&petal("default-int-i64", src, &["--default-int", "i64"]));
+    assert!(diagnostics.contains("Cannot assign a value of type i64 to 'y' of type i32"), "{}", diagnostics);
+}
+
+#[test]
+fn overwritten_stores_leave_no_nops_in_the_ir() {
+    let src = "fn main() -> i32 {\n    x := 1;\n    x = 2;\n    ret x;\n}\n";
+    let ir = stdout(&petal("no-nops", src, &["--emit", "ir"]));
+    let stores: Vec<&str> = ir.lines().filter(|line| line.starts_with("Store")).collect();
+    assert_eq!(stores, vec![r#"Store { dest: "x", src: "t2" }"#], "{}", ir);
+    assert!(!ir.contains("Nop"), "{}", ir);
+}
+
+#[test]
+fn ir_dump_lists_the_constant_pool() {
+    let src = "fn greeting() -> str {\n    ret \"hi\";\n}\nfn main() {}\n";
+    let ir = stdout(&petal("constant-pool", src, &["--emit", "ir"]));
+    assert!(ir.contains(r#"LoadConstant { dest: "t1", label: "C0" }"#), "{}", ir);
+    assert!(ir.lines().any(|line| line == r#"C0: Str("hi")"#), "{}", ir);
+}
+
+#[test]
+fn target_sets_the_size_of_statics() {
+    let src = "static n: usize = 1;\nfn main() {}\n";
+    let rp2040 = petal("target-rp2040", src, &["--target", "rp2040", "--emit", "ir"]);
+    assert!(rp2040.status.success());
+    assert!(stdout(&rp2040).contains("n: static 1 (4 bytes)"), "{}", stdout(&rp2040));
+
+    let x86_64 = petal("target-x86_64", src, &["--emit", "ir"]);
+    assert!(stdout(&x86_64).contains("n: static 1 (8 bytes)"), "{}", stdout(&x86_64));
+
+    let unknown = petal("target-unknown", src, &["--target", "riscv64"]);
+    assert!(!unknown.status.success());
+    assert!(stderr(&unknown).contains("unknown target 'riscv64'"), "{}", stderr(&unknown));
+}
+
+#[test]
+fn emit_tokens_stops_after_lexing() {
+    let src = "fn main() {\n    x := ;\n}\n";
+    assert!(stderr(&petal("tokens-parse", src, &[])).contains("Error (parse)"));
+
+    let output = petal("tokens-only", src, &["--emit", "tokens"]);
+    let tokens = stdout(&output);
+    assert!(tokens.lines().next().unwrap().ends_with("Fn"), "{}", tokens);
+    assert!(!tokens.contains("FnDef"), "{}", tokens);
+    // The parse error is never reached.
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn emit_prints_only_the_requested_stage() {
+    let ast = stdout(&petal("emit-ast", VALID, &["--emit", "ast"]));
+    assert!(ast.contains("FnDef: `main`"), "{}", ast);
+    assert!(!ast.contains("Ret("), "{}", ast);
+
+    let output = petal("emit-ir", VALID, &["--emit", "ir"]);
+    let ir = stdout(&output);
+    assert!(ir.lines().all(|line| line.contains('(') || line.contains(" {")), "{}", ir);
+    assert!(!ir.contains("FnDef"), "{}", ir);
+    assert_eq!(stderr(&output), "");
+
+    let callgraph = stdout(&petal("emit-callgraph", VALID, &["--emit", "callgraph"]));
+    assert!(!callgraph.contains("Ret(") && !callgraph.contains("digraph"), "{}", callgraph);
+}
+
+#[test]
+fn debug_prints_every_stage() {
+    let output = stdout(&petal("debug", VALID, &["--debug"]));
+    for stage in ["ret x + 1;", " Fn\n", "FnDef: `main`", "Ret(", "digraph"] {
+        assert!(output.contains(stage), "no {:?} in:\n{}", stage, output);
+    }
+}
+
+#[test]
+fn emit_asm_writes_nothing_yet() {
+    let dir = write_source("emit-asm", VALID).parent().unwrap().to_path_buf();
+    let out = dir.join("prog.s");
+    let output = petal("emit-asm", VALID, &["--emit", "asm", "-o", out.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        stderr(&output),
+        format!("note: no assembly written to {}; code generation is not implemented yet\n", out.display())
+    );
+    assert!(!out.exists());
+}
+
+#[test]
+fn errors_make_the_exit_status_fail() {
+    let output = petal("exit-status", "fn main() {\n    x := 1 +;\n}\n", &[]);
+    assert!(stderr(&output).contains("Error (parse)"), "{}", stderr(&output));
+    assert_eq!(output.status.code(), Some(1));
+
+    let lexed = petal("exit-status-tokens", "fn main() {\n    y := 2 $;\n}\n", &["--emit", "tokens"]);
+    assert_eq!(lexed.status.code(), Some(1));
+
+    let src = "fn main() {\n    x := 1;\n    c := true;\n    if c {\n        x := 2;\n    }\n}\n";
+    let warned = petal("exit-status-warning", src, &["--lint"]);
+    assert!(stderr(&warned).contains("Warning"), "{}", stderr(&warned));
+    assert!(warned.status.success());
+}
+
+#[test]
+fn a_parse_error_in_main_is_reported_once() {
+    let output = petal("one-parse-error", "fn main() {\n    x := 1\n}\n", &[]);
+    let diagnostics = stderr(&output);
+    assert_eq!(diagnostics.lines().count(), 1, "{}", diagnostics);
+    assert!(diagnostics.starts_with("Error (parse)"), "{}", diagnostics);
+}